@@ -0,0 +1,97 @@
+use crate::vec3::Vec3;
+use crate::ray::Ray;
+use crate::transform::Transform;
+use crate::math::GAMMA_3;
+
+use Vec3 as Point3;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// Box containing nothing, the identity of `union`.
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY, true),
+            max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY, true)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|i| self.min[i] > self.max[i])
+    }
+
+    pub fn grow(&self, point: Point3) -> Aabb {
+        Aabb {
+            min: Point3::new(self.min.x().min(point.x()), self.min.y().min(point.y()), self.min.z().min(point.z()), true),
+            max: Point3::new(self.max.x().max(point.x()), self.max.y().max(point.y()), self.max.z().max(point.z()), true)
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        self.grow(other.min).grow(other.max)
+    }
+
+    pub fn center(&self) -> Point3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Index of the axis along which the box is the largest.
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Bounds of the box's eight corners after applying `matrix`.
+    pub fn transformed(&self, matrix: &[[f64; 4]; 4]) -> Aabb {
+        (0..8).fold(Aabb::empty(), |bounds, corner| {
+            let pick = |axis: usize| if corner & (1 << axis) == 0 { self.min[axis] } else { self.max[axis] };
+            bounds.grow(Point3::new(pick(0), pick(1), pick(2), true).transform(matrix))
+        })
+    }
+
+    /// Conservative slab test, returning whether the ray enters the box
+    /// within `[t_min, t_max]`. Rays parallel to a slab are tested against
+    /// it directly, as `0 * inf` is not a number, and exit distances are
+    /// widened by their rounding error so rays grazing an edge are kept.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut tmin = t_min;
+        let mut tmax = t_max;
+
+        for i in 0..3 {
+            let origin = ray.origin()[i];
+
+            if ray.direction()[i] == 0.0 {
+                if origin < self.min[i] || origin > self.max[i] {
+                    return false;
+                }
+
+                continue;
+            }
+
+            let t1 = (self.min[i] - origin) * ray.direction_inv()[i];
+            let t2 = (self.max[i] - origin) * ray.direction_inv()[i];
+            let far = t1.max(t2);
+
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(far + 2.0 * GAMMA_3 * far.abs());
+        }
+
+        tmin <= tmax
+    }
+}
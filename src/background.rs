@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use crate::vec3::Vec3;
+use crate::image::Image;
+use crate::distribution::Distribution2D;
+use crate::brdf::luminance;
+use crate::sky::Sky;
+
+use Vec3 as Color;
+
+/// Radiance arriving from directions that miss every object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Color(Color),
+    /// Blend from `bottom`, looking straight down, to `top`, looking up.
+    Gradient { bottom: Color, top: Color },
+    Environment(EnvironmentMap),
+    Sky(Sky)
+}
+
+impl Background {
+    pub fn value(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.normalized().y() + 1.0);
+                *bottom * (1.0 - t) + *top * t
+            }
+            Background::Environment(environment) => environment.value(direction),
+            Background::Sky(sky) => sky.value(direction)
+        }
+    }
+
+    /// Whether the background is importance sampled as a light. Other
+    /// backgrounds are only reached by rays escaping the scene.
+    pub fn is_sampled(&self) -> bool {
+        matches!(self, Background::Environment(_) | Background::Sky(_))
+    }
+
+    /// Direction towards the background and its density with respect to
+    /// solid angle, zero when the background is not sampled.
+    pub fn sample(&self, u: (f64, f64)) -> (Vec3, f64) {
+        match self {
+            Background::Environment(environment) => environment.sample(u),
+            Background::Sky(sky) => sky.sample(u),
+            _ => (Vec3::new(0.0, 1.0, 0.0, false), 0.0)
+        }
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        match self {
+            Background::Environment(environment) => environment.pdf(direction),
+            Background::Sky(sky) => sky.pdf(direction),
+            _ => 0.0
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::Color(Color::new(0.08, 0.18, 0.29, false))
+    }
+}
+
+/// Latitude-longitude environment map, with the top row looking up (`+y`)
+/// and `u` turning from `+x` towards `+z`. `rotation` turns the map around
+/// the `y` axis, in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    pub image: Arc<Image>,
+    pub rotation: f64,
+    pub intensity: f64,
+    distribution: Arc<Distribution2D>
+}
+
+impl EnvironmentMap {
+    /// Builds the sampling distribution from the luminance of the pixels,
+    /// weighted by the solid angle they cover. An empty image is a black map.
+    pub fn new(image: Arc<Image>, rotation: f64, intensity: f64) -> EnvironmentMap {
+        let image = if image.width == 0 || image.height == 0 {
+            Arc::new(Image::new(1, 1, vec![Color::new(0.0, 0.0, 0.0, false)]))
+        } else {
+            image
+        };
+        let (width, height) = (image.width, image.height);
+        let mut weights = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let sin_theta = (std::f64::consts::PI * (y as f64 + 0.5) / height as f64).sin();
+
+            for x in 0..width {
+                weights.push(luminance(image.pixel(x, y)) * sin_theta);
+            }
+        }
+
+        let distribution = Arc::new(Distribution2D::new(&weights, width, height));
+
+        EnvironmentMap { image, rotation, intensity, distribution }
+    }
+
+    /// Map coordinates of a direction, `v` going from the top row (0) to
+    /// the bottom one (1).
+    fn to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let pi = std::f64::consts::PI;
+        let direction = direction.normalized();
+        let phi = (direction.z().atan2(direction.x()) - self.rotation.to_radians()).rem_euclid(2.0 * pi);
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+
+        (phi / (2.0 * pi), theta / pi)
+    }
+
+    fn to_direction(&self, uv: (f64, f64)) -> Vec3 {
+        let pi = std::f64::consts::PI;
+        let phi = uv.0 * 2.0 * pi + self.rotation.to_radians();
+        let theta = uv.1 * pi;
+
+        Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false)
+    }
+
+    /// Bilinear lookup, wrapping around horizontally.
+    pub fn value(&self, direction: Vec3) -> Color {
+        let (u, v) = self.to_uv(direction);
+        let (width, height) = (self.image.width as i64, self.image.height as i64);
+        let x = u * width as f64 - 0.5;
+        let y = v * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let texel = |dx: i64, dy: i64| {
+            let px = (x0 as i64 + dx).rem_euclid(width) as usize;
+            let py = (y0 as i64 + dy).clamp(0, height - 1) as usize;
+            self.image.pixel(px, py)
+        };
+
+        let color = (texel(0, 0) * (1.0 - tx) + texel(1, 0) * tx) * (1.0 - ty)
+            + (texel(0, 1) * (1.0 - tx) + texel(1, 1) * tx) * ty;
+
+        color * self.intensity
+    }
+
+    /// Direction drawn proportionally to the map's luminance and its
+    /// density with respect to solid angle, zero for degenerate samples.
+    pub fn sample(&self, u: (f64, f64)) -> (Vec3, f64) {
+        let (uv, pdf) = self.distribution.sample(u);
+        let sin_theta = (uv.1 * std::f64::consts::PI).sin();
+
+        if pdf <= 0.0 || sin_theta <= 0.0 {
+            return (self.to_direction(uv), 0.0);
+        }
+
+        (self.to_direction(uv), pdf / (2.0 * std::f64::consts::PI * std::f64::consts::PI * sin_theta))
+    }
+
+    /// Density of `sample` for `direction`, with respect to solid angle.
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        let uv = self.to_uv(direction);
+        let sin_theta = (uv.1 * std::f64::consts::PI).sin();
+
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(uv) / (2.0 * std::f64::consts::PI * std::f64::consts::PI * sin_theta)
+    }
+}
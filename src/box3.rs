@@ -1,16 +1,27 @@
 use crate::vec3::Vec3;
 use crate::ray::Ray;
-use crate::hit::{HitRecord, Hit};
+use crate::hit::{HitRecord, Hit, Visibility};
 use crate::material::Material;
 use crate::transform::TransformMatrix;
+use crate::math::{GAMMA_3, GAMMA_5};
 
 use Vec3 as Point3;
 
+/// Unit vector along `axis`, pointing towards `sign`.
+fn axis_normal(axis: usize, sign: f64) -> Vec3 {
+    let mut n = [0.0; 3];
+    n[axis] = sign;
+
+    Vec3::new(n[0], n[1], n[2], false)
+}
+
 pub struct Box3 {
     pub min_bound: Point3,
     pub max_bound: Point3,
     pub material: Material,
-    pub transform_matrix: Option<TransformMatrix>
+    pub transform_matrix: Option<TransformMatrix>,
+    pub name: String,
+    pub visibility: Visibility
 }
 
 impl Box3 {
@@ -19,58 +30,145 @@ impl Box3 {
             min_bound,
             max_bound,
             material,
-            transform_matrix
+            transform_matrix,
+            name: String::new(),
+            visibility: Visibility::default()
         }
     }
 
+    /// Positive axis normal of the face closest to `point`, the same for
+    /// opposite faces. Points on an edge or a corner get the normal of one
+    /// of the faces meeting there.
     pub fn normal_at(&self, point: Point3) -> Vec3 {
-        let normal_x: f64 = if (point.x() - self.min_bound.x()).abs() < 0.000001
-                            || (point.x() - self.max_bound.x()).abs() < 0.000001
-                            { 1.0 } else { 0.0 };
+        let mut closest = (f64::INFINITY, 0);
+
+        for i in 0..3 {
+            let distance = (point[i] - self.min_bound[i]).abs().min((point[i] - self.max_bound[i]).abs());
+
+            if distance < closest.0 {
+                closest = (distance, i);
+            }
+        }
+
+        axis_normal(closest.1, 1.0)
+    }
 
-        let normal_y: f64 = if (point.y() - self.min_bound.y()).abs() < 0.000001
-                            || (point.y() - self.max_bound.y()).abs() < 0.000001
-                            { 1.0 } else { 0.0 };
+    /// Per-face planar mapping over the extent of the box: faces along `x`
+    /// map `(z, y)`, faces along `y` map `(x, z)` and faces along `z` map
+    /// `(x, y)`.
+    pub fn uv_at(&self, point: Point3, normal: Vec3) -> ((f64, f64), Vec3, Vec3) {
+        let (u_axis, v_axis) = if normal.x().abs() > 0.5 {
+            (2, 1)
+        } else if normal.y().abs() > 0.5 {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
 
-        let normal_z: f64 = if (point.z() - self.min_bound.z()).abs() < 0.000001
-                            || (point.z() - self.max_bound.z()).abs() < 0.000001
-                            { 1.0 } else { 0.0 };
+        let extent = self.max_bound - self.min_bound;
+        let coordinate = |axis: usize| (point[axis] - self.min_bound[axis]) / extent[axis];
+        let derivative = |axis: usize| {
+            let mut d = [0.0; 3];
+            d[axis] = extent[axis];
+            Vec3::new(d[0], d[1], d[2], false)
+        };
 
-        Vec3::new(normal_x, normal_y, normal_z, false).normalized()
+        ((coordinate(u_axis), coordinate(v_axis)), derivative(u_axis), derivative(v_axis))
     }
 }
 
 impl Hit for Box3 {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let mut tmin: f64 = t_min;
-        let mut tmax: f64 = t_max;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        // the slabs the ray enters and leaves through give the face hit,
+        // rays starting inside the box hit the face they leave through. Exit
+        // distances are widened by their rounding error so rays through an
+        // edge or a corner hit the box.
+        let mut near = (f64::NEG_INFINITY, 0);
+        let mut far = (f64::INFINITY, 0);
 
         for i in 0..3 {
-            let t1 = (self.min_bound[i] - ray.origin()[i]) * ray.direction_inv()[i];
-            let t2 = (self.max_bound[i] - ray.origin()[i]) * ray.direction_inv()[i];
+            let origin = ray.origin()[i];
+
+            // parallel to the slab, the ray is either always or never inside
+            if ray.direction()[i] == 0.0 {
+                if origin < self.min_bound[i] || origin > self.max_bound[i] {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let t1 = (self.min_bound[i] - origin) * ray.direction_inv()[i];
+            let t2 = (self.max_bound[i] - origin) * ray.direction_inv()[i];
 
-            tmin = tmin.max(t1.min(t2));
-            tmax = tmax.min(t1.max(t2));
+            if t1.min(t2) > near.0 {
+                near = (t1.min(t2), i);
+            }
+
+            let exit = t1.max(t2) + 2.0 * GAMMA_3 * t1.max(t2).abs();
+
+            if exit < far.0 {
+                far = (exit, i);
+            }
+        }
+
+        if near.0 > far.0 {
+            return None;
         }
 
-        if tmin > tmax {
+        // entering through a face points against the ray, leaving along it
+        let (t, axis, sign) = if near.0 >= t_min {
+            (near.0, near.1, -ray.direction()[near.1].signum())
+        } else {
+            (far.0, far.1, ray.direction()[far.1].signum())
+        };
+
+        if t < t_min || t > t_max || !t.is_finite() {
             return None;
         }
-        
-        let hit_point = ray.at(tmin);
-        let normal = self.normal_at(hit_point);
+
+        // the point lies exactly on the face's plane
+        let on_face = ray.at(t);
+        let bound = if sign > 0.0 { self.max_bound[axis] } else { self.min_bound[axis] };
+        let mut coordinates = [on_face.x(), on_face.y(), on_face.z()];
+        coordinates[axis] = bound;
+        let hit_point = Point3::new(coordinates[0], coordinates[1], coordinates[2], true);
+        // exact across the face, following the ray along it
+        let mut point_error = [coordinates[0].abs() * GAMMA_5, coordinates[1].abs() * GAMMA_5, coordinates[2].abs() * GAMMA_5];
+        point_error[axis] = 0.0;
+        let normal = axis_normal(axis, sign);
         let front_face = ray.direction().dot(normal) < 0.0;
+        let (uv, dpdu, dpdv) = self.uv_at(hit_point, normal);
 
         Some(HitRecord {
-            t_min: tmin,
+            t_min: t,
             point: hit_point,
+            point_error: Vec3::new(point_error[0], point_error[1], point_error[2], false),
+            object_point: hit_point,
             normal: if front_face { normal } else { -normal },
+            geometric_normal: if front_face { normal } else { -normal },
+            uv,
+            dpdu,
+            dpdv,
+            dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+            dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
             front_face,
-            material: self.material
+            material: &self.material,
+            name: &self.name
         })
     }
 
     fn transform_matrix(&self) -> Option<&TransformMatrix> {
         self.transform_matrix.as_ref()
     }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
 }
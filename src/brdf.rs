@@ -2,55 +2,337 @@ use rand::Rng;
 
 use crate::vec3::Vec3;
 use crate::material::Material;
+use crate::frame::Frame;
+use crate::multiscatter::{ggx_average_albedo, multiple_scattering};
 
-pub fn d(alpha: f64, n: Vec3, h: Vec3) -> f64 {
-    let alpha2 = alpha.powf(2.0);
-    let n_dot_h2 = n.dot(h).max(0.0).powf(2.0);
+/// Anisotropic GGX distribution of microfacet normals. `h` is expressed in
+/// the local shading frame.
+pub fn d(alpha_x: f64, alpha_z: f64, h: Vec3) -> f64 {
+    if h.y() <= 0.0 {
+        return 0.0;
+    }
 
-    alpha2 / (std::f64::consts::PI * (n_dot_h2 * (alpha2 - 1.0) + 1.0).powf(2.0)).max(0.000001)
+    let e = (h.x() / alpha_x).powf(2.0) + (h.z() / alpha_z).powf(2.0) + h.y() * h.y();
+
+    1.0 / (std::f64::consts::PI * alpha_x * alpha_z * e * e).max(0.000001)
+}
+
+/// Smith auxiliary function for the anisotropic GGX distribution.
+pub fn lambda(alpha_x: f64, alpha_z: f64, x: Vec3) -> f64 {
+    let cos2_theta = x.y() * x.y();
+
+    if cos2_theta <= 0.0 {
+        return f64::INFINITY;
+    }
+
+    let alpha2_tan2_theta = (alpha_x * alpha_x * x.x() * x.x() + alpha_z * alpha_z * x.z() * x.z()) / cos2_theta;
+
+    0.5 * (-1.0 + (1.0 + alpha2_tan2_theta).sqrt())
 }
 
-pub fn g1(alpha: f64, n: Vec3, x: Vec3) -> f64 {
-    let n_dot_x = n.dot(x).max(0.0);
-    let k = alpha / 2.0;
+pub fn g1(alpha_x: f64, alpha_z: f64, x: Vec3) -> f64 {
+    if x.y() <= 0.0 {
+        return 0.0;
+    }
 
-    n_dot_x / (n_dot_x * (1.0 - k) + k).max(0.000001)
+    1.0 / (1.0 + lambda(alpha_x, alpha_z, x))
 }
 
-pub fn g(alpha: f64, n: Vec3, v: Vec3, l: Vec3) -> f64 {
-    g1(alpha, n, v) * g1(alpha, n, l)
+pub fn g(alpha_x: f64, alpha_z: f64, v: Vec3, l: Vec3) -> f64 {
+    g1(alpha_x, alpha_z, v) * g1(alpha_x, alpha_z, l)
 }
 
 pub fn f(f0: Vec3, v: Vec3, h: Vec3) -> Vec3 {
     f0 + (Vec3::new(1.0, 1.0, 1.0, false) - f0) * (1.0 - v.dot(h).max(0.0)).powf(5.0)
 }
 
-pub fn brdf(material: Material, n: Vec3, v: Vec3, l: Vec3) -> Vec3 {
+/// Exact unpolarized Fresnel reflectance at a dielectric interface, where
+/// `eta` is the ratio of the incident to the transmitted index of refraction.
+/// Returns 1.0 on total internal reflection.
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = eta * eta * (1.0 - cos_theta_i * cos_theta_i);
+
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    let r_s = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_p = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    0.5 * (r_s * r_s + r_p * r_p)
+}
+
+fn fresnel_conductor_channel(cos_theta_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2_theta = cos_theta_i * cos_theta_i;
+    let sin2_theta = 1.0 - cos2_theta;
+    let eta2 = eta * eta;
+    let k2 = k * k;
+
+    let t0 = eta2 - k2 - sin2_theta;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+    let t1 = a2_plus_b2 + cos2_theta;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_theta_i * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos2_theta * a2_plus_b2 + sin2_theta * sin2_theta;
+    let t4 = t2 * sin2_theta;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+
+    0.5 * (r_s + r_p)
+}
+
+/// Exact Fresnel reflectance of a conductor with complex index of refraction
+/// `eta + i k`, evaluated per RGB channel against air.
+pub fn fresnel_conductor(cos_theta_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+
+    Vec3::new(
+        fresnel_conductor_channel(cos_theta_i, eta.x(), k.x()),
+        fresnel_conductor_channel(cos_theta_i, eta.y(), k.y()),
+        fresnel_conductor_channel(cos_theta_i, eta.z(), k.z()),
+        false
+    )
+}
+
+pub fn luminance(c: Vec3) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta).clamp(0.0, 1.0).powf(5.0)
+}
+
+/// Base color normalized by its luminance, used by the tint parameters.
+fn tint(albedo: Vec3) -> Vec3 {
+    let lum = luminance(albedo);
+
+    if lum > 0.0 { albedo / lum } else { Vec3::new(1.0, 1.0, 1.0, false) }
+}
+
+/// Reflectance of the specular lobe, blending the tinted dielectric Fresnel
+/// term derived from the IOR with either the measured conductor Fresnel term
+/// or, without one, a Schlick term using the base color of metals. A thin
+/// film replaces both with the interference reflectance over the same base.
+pub fn specular_fresnel(material: &Material, cos_theta: f64) -> Vec3 {
+    let white = Vec3::new(1.0, 1.0, 1.0, false);
+    let black = Vec3::new(0.0, 0.0, 0.0, false);
+    let specular_tint = lerp(white, tint(material.albedo), material.specular_tint);
+
+    if let Some(film) = &material.thin_film {
+        let ior = Vec3::new(material.ior, material.ior, material.ior, false);
+        let dielectric = film.reflectance(cos_theta, 1.0, ior, black) * specular_tint;
+        let conductor = match material.conductor {
+            Some(conductor) => film.reflectance(cos_theta, 1.0, conductor.eta, conductor.k),
+            None => film.reflectance(cos_theta, 1.0, reflectance_to_ior(material.albedo), black)
+        };
+
+        return lerp(dielectric, conductor, material.metallic);
+    }
+
+    let dielectric = fresnel_dielectric(cos_theta, 1.0 / material.ior) * specular_tint;
+    let conductor = match material.conductor {
+        Some(conductor) => fresnel_conductor(cos_theta, conductor.eta, conductor.k),
+        None => material.albedo + (white - material.albedo) * schlick_weight(cos_theta)
+    };
+
+    lerp(dielectric, conductor, material.metallic)
+}
+
+/// Real index of refraction with the given reflectance at normal incidence,
+/// per channel.
+fn reflectance_to_ior(f0: Vec3) -> Vec3 {
+    let channel = |f0: f64| {
+        let r = f0.clamp(0.0, 0.99).sqrt();
+        (1.0 + r) / (1.0 - r)
+    };
+
+    Vec3::new(channel(f0.x()), channel(f0.y()), channel(f0.z()), false)
+}
+
+/// Hemispherical average of `specular_fresnel`, using fitted closed forms
+/// where available. Thin films are ignored here, their average reflectance
+/// stays close to the one of the uncoated base.
+fn average_specular_fresnel(material: &Material) -> Vec3 {
+    let white = Vec3::new(1.0, 1.0, 1.0, false);
+    let eta = material.ior.max(1.0);
+    let dielectric = (eta - 1.0) / (4.08567 + 1.00071 * eta) * lerp(white, tint(material.albedo), material.specular_tint);
+
+    if material.metallic <= 0.0 {
+        return dielectric;
+    }
+
+    let conductor = match material.conductor {
+        Some(conductor) => conductor.average_fresnel,
+        None => material.albedo + (white - material.albedo) / 21.0
+    };
+
+    lerp(dielectric, conductor, material.metallic)
+}
+
+fn clearcoat_alpha(material: &Material) -> f64 {
+    material.clearcoat_roughness.max(0.001)
+}
+
+/// Principled (Disney-style) reflectance: a Burley diffuse lobe blended with
+/// a Hanrahan–Krueger subsurface approximation, sheen, an anisotropic GGX
+/// specular lobe and a clearcoat layer on top. Specular transmission is
+/// handled separately by the renderer.
+pub fn brdf(material: &Material, frame: &Frame, v: Vec3, l: Vec3) -> Vec3 {
+    let white = Vec3::new(1.0, 1.0, 1.0, false);
+    let v = frame.to_local(v.normalized());
+    let l = frame.to_local(l.normalized());
+
+    if v.y() <= 0.0 || l.y() <= 0.0 {
+        return Vec3::new(0.0, 0.0, 0.0, false);
+    }
+
+    if let Some(measured) = &material.measured {
+        return measured.eval(v, l);
+    }
+
     let h = (v + l).normalized();
-    let f0 = Vec3::new(0.3, 0.3, 0.3, false) * (1.0 - material.metallic) + material.albedo * material.metallic;
-    let f = f(f0, v, h);
-    let d = d(material.roughness, n, h);
-    let g = g(material.roughness, n, v, l);
+    let l_dot_h = l.dot(h).max(0.0);
+    let fl = schlick_weight(l.y());
+    let fv = schlick_weight(v.y());
+    let fh = schlick_weight(l_dot_h);
+
+    // renormalized diffuse with retro-reflection, blended towards the
+    // subsurface lobe
+    let energy_bias = 0.5 * material.roughness;
+    let energy_factor = 1.0 - material.roughness * (1.0 - 1.0 / 1.51);
+    let fd90 = energy_bias + 2.0 * material.roughness * l_dot_h * l_dot_h;
+    let fd = (1.0 + (fd90 - 1.0) * fl) * (1.0 + (fd90 - 1.0) * fv) * energy_factor;
+    let fss90 = material.roughness * l_dot_h * l_dot_h;
+    let fss = (1.0 + (fss90 - 1.0) * fl) * (1.0 + (fss90 - 1.0) * fv);
+    // the volumetric term is clamped to keep the lobe from gaining energy
+    // at grazing angles
+    let ss = 1.25 * (fss * ((1.0 / (l.y() + v.y())).min(1.0) - 0.5) + 0.5) * energy_factor;
+    let diffuse = material.albedo / std::f64::consts::PI * (fd * (1.0 - material.subsurface) + ss * material.subsurface);
+
+    let sheen = lerp(white, tint(material.albedo), material.sheen_tint) * material.sheen * fh;
+
+    let (alpha_x, alpha_z) = material.alpha();
+    let denominator = (4.0 * v.y() * l.y()).max(0.000001);
+    let f = specular_fresnel(material, l_dot_h);
+    let f_avg = average_specular_fresnel(material);
+    let specular = f * (d(alpha_x, alpha_z, h) * g(alpha_x, alpha_z, v, l) / denominator)
+        + multiple_scattering(f_avg, v.y(), l.y(), (alpha_x * alpha_z).sqrt());
 
-    let numerator = d * g * f;
-    let denominator = (4.0 * n.dot(v).max(0.0) * n.dot(l).max(0.0)).max(0.000001);
-    let specular = numerator / denominator;
-    let diffuse = material.albedo / std::f64::consts::PI;
+    let alpha_c = clearcoat_alpha(material);
+    let fc = 0.04 + 0.96 * fh;
+    let clearcoat = material.clearcoat * fc * d_clearcoat(alpha_c, h) * g(alpha_c, alpha_c, v, l) / denominator;
 
-    let ks = f;
-    let kd = (Vec3::new(1.0, 1.0, 1.0, false) - ks) * (1.0 - material.metallic);
+    let kd = (white - f) * (1.0 - material.metallic);
+    let base = kd * (diffuse + sheen) + specular;
 
-    kd * diffuse + ks * specular
+    base * (1.0 - material.clearcoat * fc) + clearcoat
 }
 
-pub fn sample_ggx_vndf(ve: Vec3, alpha: f64) -> Vec3 {
+fn d_clearcoat(alpha: f64, h: Vec3) -> f64 {
+    d(alpha, alpha, h)
+}
+
+/// Relative probabilities of sampling the diffuse lobe, the
+/// multiple-scattering part of the specular lobe, the single-scattering
+/// specular lobe and the clearcoat lobe. The first two are both sampled
+/// with a cosine distribution.
+fn lobe_weights(material: &Material) -> (f64, f64, f64, f64) {
+    let (alpha_x, alpha_z) = material.alpha();
+    let diffuse = 1.0 - material.metallic;
+    let multiple_scattering = 1.0 - ggx_average_albedo((alpha_x * alpha_z).sqrt());
+    let specular = 1.0;
+    let clearcoat = 0.25 * material.clearcoat;
+    let total = diffuse + multiple_scattering + specular + clearcoat;
+
+    (diffuse / total, multiple_scattering / total, specular / total, clearcoat / total)
+}
+
+/// Probability density, per unit solid angle, of `sample_brdf` returning `l`.
+pub fn pdf(material: &Material, frame: &Frame, v: Vec3, l: Vec3) -> f64 {
+    let v = frame.to_local(v.normalized());
+    let l = frame.to_local(l.normalized());
+
+    if v.y() <= 0.0 || l.y() <= 0.0 {
+        return 0.0;
+    }
+
+    let h = (v + l).normalized();
+    let (diffuse_weight, multiple_scattering_weight, specular_weight, clearcoat_weight) = lobe_weights(material);
+    let (alpha_x, alpha_z) = material.alpha();
+    let alpha_c = clearcoat_alpha(material);
+
+    // visible normal density mapped through the reflection jacobian
+    let pdf_diffuse = l.y() / std::f64::consts::PI;
+    let pdf_specular = g1(alpha_x, alpha_z, v) * d(alpha_x, alpha_z, h) / (4.0 * v.y());
+    let pdf_clearcoat = g1(alpha_c, alpha_c, v) * d_clearcoat(alpha_c, h) / (4.0 * v.y());
+
+    (diffuse_weight + multiple_scattering_weight) * pdf_diffuse + specular_weight * pdf_specular + clearcoat_weight * pdf_clearcoat
+}
+
+/// Reflection lobe of `brdf` a direction was sampled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lobe {
+    Diffuse,
+    Specular,
+    Clearcoat
+}
+
+/// Importance samples an incoming direction for the reflection lobes of
+/// `brdf`. Returns `None` when the sample falls below the surface.
+pub fn sample_brdf(material: &Material, frame: &Frame, v: Vec3) -> Option<Vec3> {
+    sample_brdf_lobe(material, frame, v).map(|(direction, _)| direction)
+}
+
+/// `sample_brdf`, also returning the lobe the direction was drawn from.
+pub fn sample_brdf_lobe(material: &Material, frame: &Frame, v: Vec3) -> Option<(Vec3, Lobe)> {
+    let mut rng = rand::thread_rng();
+    let ve = frame.to_local(v.normalized());
+
+    if ve.y() <= 0.0 {
+        return None;
+    }
+
+    let (diffuse_weight, multiple_scattering_weight, specular_weight, _) = lobe_weights(material);
+    let cosine_weight = diffuse_weight + multiple_scattering_weight;
+    let u = rng.gen::<f64>();
+
+    let (l, lobe) = if u < cosine_weight {
+        let r = rng.gen::<f64>().sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+        // energy lost by the specular lobe is still a specular reflection
+        let lobe = if u < diffuse_weight { Lobe::Diffuse } else { Lobe::Specular };
+        (Vec3::new(r * phi.cos(), (1.0 - r * r).max(0.0).sqrt(), r * phi.sin(), false), lobe)
+    } else if u < cosine_weight + specular_weight {
+        let (alpha_x, alpha_z) = material.alpha();
+        ((-ve).reflect(sample_ggx_vndf(ve, alpha_x, alpha_z)), Lobe::Specular)
+    } else {
+        let alpha_c = clearcoat_alpha(material);
+        ((-ve).reflect(sample_ggx_vndf(ve, alpha_c, alpha_c)), Lobe::Clearcoat)
+    };
+
+    if l.y() <= 0.0 {
+        return None;
+    }
+
+    Some((frame.to_world(l).normalized(), lobe))
+}
+
+pub fn sample_ggx_vndf(ve: Vec3, alpha_x: f64, alpha_z: f64) -> Vec3 {
     let mut rng = rand::thread_rng();
-    let u1 = rng.gen::<f64>();
-    let u2 = rng.gen::<f64>();
 
-    let alpha_x = alpha;
-    let alpha_z = alpha;
+    ggx_vndf(ve, alpha_x, alpha_z, rng.gen::<f64>(), rng.gen::<f64>())
+}
 
+/// Maps the uniform numbers `u1` and `u2` to a GGX normal distributed
+/// according to the distribution of normals visible from `ve`.
+pub fn ggx_vndf(ve: Vec3, alpha_x: f64, alpha_z: f64, u1: f64, u2: f64) -> Vec3 {
     let vh = Vec3::new(ve.x() * alpha_x, ve.y(), ve.z() * alpha_z, false).normalized();
 
     let lensq = vh.x() * vh.x() + vh.z() * vh.z();
@@ -60,7 +342,7 @@ pub fn sample_ggx_vndf(ve: Vec3, alpha: f64) -> Vec3 {
     } else {
         Vec3::new(1.0, 0.0, 0.0, false)
     };
-    let vt2 = vh.cross(vt1);
+    let vt2 = vt1.cross(vh);
 
     let r = u1.sqrt();
     let phi = 2.0 * std::f64::consts::PI * u2;
@@ -74,6 +356,15 @@ pub fn sample_ggx_vndf(ve: Vec3, alpha: f64) -> Vec3 {
     Vec3::new(alpha_x * nh.x(), nh.y().max(0.0), alpha_z * nh.z(), false).normalized()
 }
 
+/// Samples a GGX microfacet normal around the frame's normal, visible from
+/// direction `v`. The result is in world space.
+pub fn sample_microfacet_normal(frame: &Frame, v: Vec3, alpha_x: f64, alpha_z: f64) -> Vec3 {
+    let ve = frame.to_local(v).normalized();
+    let m = sample_ggx_vndf(ve, alpha_x, alpha_z);
+
+    frame.to_world(m).normalized()
+}
+
 pub fn perturb(v: &Vec3, roughness: f64) -> Vec3 {
     let mut rng = rand::thread_rng();
     let offset_x: f64 = rng.gen_range(-0.5..0.5) * roughness * roughness;
@@ -0,0 +1,96 @@
+use crate::vec3::Vec3;
+use crate::hit::HitRecord;
+use crate::material::Material;
+use crate::texture::Texture;
+
+/// Step in uv used to differentiate height maps.
+const BUMP_DELTA: f64 = 0.0005;
+
+/// Unit tangent along `dpdu` and bitangent along `dpdv`, both orthogonal to
+/// `normal`. The bitangent follows `dpdv` even when the uv mapping is
+/// mirrored.
+fn tangent_basis(hit_record: &HitRecord, normal: Vec3) -> (Vec3, Vec3) {
+    let tangent = hit_record.dpdu - normal * normal.dot(hit_record.dpdu);
+
+    let tangent = if tangent.length_squared() > 1e-12 {
+        tangent.normalized()
+    } else {
+        normal.orthonormal_basis().0
+    };
+
+    let bitangent = normal.cross(tangent);
+
+    if bitangent.dot(hit_record.dpdv) < 0.0 { (tangent, -bitangent) } else { (tangent, bitangent) }
+}
+
+/// Normal read from a tangent-space normal map, whose channels encode the
+/// tangent, bitangent and normal components remapped to `[0, 1]`.
+pub fn normal_map(hit_record: &HitRecord, texture: &Texture, view: Vec3) -> Vec3 {
+    let normal = hit_record.normal;
+    let (tangent, bitangent) = tangent_basis(hit_record, normal);
+    let value = texture.value(hit_record, view) * 2.0 - Vec3::new(1.0, 1.0, 1.0, false);
+    let mapped = tangent * value.x() + bitangent * value.y() + normal * value.z();
+
+    if mapped.length_squared() > 1e-12 { mapped.normalized() } else { normal }
+}
+
+/// Normal of the surface displaced along its normal by `scale` times the
+/// first channel of a height map, ignoring the curvature of the surface.
+pub fn bump_map(hit_record: &HitRecord, texture: &Texture, scale: f64, view: Vec3) -> Vec3 {
+    let height = |du: f64, dv: f64| {
+        // textures evaluated in world or object space need a matching point
+        let offset = hit_record.dpdu * du + hit_record.dpdv * dv;
+        let shifted = HitRecord {
+            point: hit_record.point + offset,
+            object_point: hit_record.object_point + offset,
+            uv: (hit_record.uv.0 + du, hit_record.uv.1 + dv),
+            ..*hit_record
+        };
+
+        scale * texture.value(&shifted, view).x()
+    };
+
+    let dhdu = (height(BUMP_DELTA, 0.0) - height(-BUMP_DELTA, 0.0)) / (2.0 * BUMP_DELTA);
+    let dhdv = (height(0.0, BUMP_DELTA) - height(0.0, -BUMP_DELTA)) / (2.0 * BUMP_DELTA);
+
+    let normal = hit_record.normal;
+    let dpdu = hit_record.dpdu + normal * dhdu;
+    let dpdv = hit_record.dpdv + normal * dhdv;
+    let bumped = dpdu.cross(dpdv);
+
+    if bumped.length_squared() <= 1e-24 {
+        return normal;
+    }
+
+    let bumped = bumped.normalized();
+
+    if bumped.dot(normal) < 0.0 { -bumped } else { bumped }
+}
+
+/// Shading normal after applying the material's normal map then bump map.
+/// Normals turned away from the viewer are bent back towards the silhouette,
+/// which would otherwise render black.
+pub fn shading_normal(material: &Material, hit_record: &HitRecord, view: Vec3) -> Vec3 {
+    if material.normal_map.is_none() && material.bump_map.is_none() {
+        return hit_record.normal;
+    }
+
+    let mut record = *hit_record;
+
+    if let Some(texture) = &material.normal_map {
+        record.normal = normal_map(&record, texture, view);
+    }
+
+    if let Some(texture) = &material.bump_map {
+        record.normal = bump_map(&record, texture, material.bump_scale, view);
+    }
+
+    let view = view.normalized();
+    let cos_theta = record.normal.dot(view);
+
+    if cos_theta < 0.01 {
+        record.normal = (record.normal + view * (0.01 - cos_theta)).normalized();
+    }
+
+    record.normal
+}
@@ -1,5 +1,5 @@
 use crate::vec3::Vec3;
-use crate::ray::Ray;
+use crate::ray::{Ray, Differentials};
 
 use Vec3 as Point3;
 
@@ -40,5 +40,21 @@ impl Camera {
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin
         )
     }
+
+    /// Ray through `(s, t)` carrying differentials towards `(s + ds, t)` and
+    /// `(s, t + dt)`, where `ds` and `dt` usually span a pixel divided by the
+    /// number of samples along each axis.
+    pub fn get_ray_differential(&self, s: f64, t: f64, ds: f64, dt: f64) -> Ray {
+        let ray = self.get_ray(s, t);
+        let rx = self.get_ray(s + ds, t);
+        let ry = self.get_ray(s, t + dt);
+
+        Ray::with_differentials(ray.origin(), ray.direction(), Some(Differentials {
+            rx_origin: rx.origin(),
+            rx_direction: rx.direction(),
+            ry_origin: ry.origin(),
+            ry_direction: ry.direction()
+        }))
+    }
 }
 
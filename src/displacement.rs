@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::vec3::Vec3;
+use crate::hit::HitRecord;
+use crate::mesh::Mesh;
+use crate::texture::Texture;
+use crate::transform::Transform;
+
+use Vec3 as Point3;
+
+/// Maximum number of subdivision passes, each splitting edges in half.
+const MAX_SUBDIVISIONS: u32 = 8;
+
+/// Offset applied to the vertices of a tessellated mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Displacement {
+    /// Along the vertex normal by `scale` times the first channel.
+    Scalar { texture: Texture, scale: f64 },
+    /// By the texture's color read as an object space vector, times `scale`.
+    Vector { texture: Texture, scale: f64 }
+}
+
+/// Target edge size of adaptive tessellation, measured in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tessellation {
+    /// Splits edges longer than the given length.
+    EdgeLength(f64),
+    /// Splits edges spanning more than `pixels` pixels when seen from `eye`,
+    /// `pixel_angle` being the angle covered by a pixel, in radians.
+    ScreenSpace { eye: Point3, pixel_angle: f64, pixels: f64 }
+}
+
+impl Tessellation {
+    pub fn splits(&self, a: Point3, b: Point3) -> bool {
+        let length = (b - a).length();
+
+        match *self {
+            Tessellation::EdgeLength(target) => length > target,
+            Tessellation::ScreenSpace { eye, pixel_angle, pixels } => {
+                let distance = ((a + b) * 0.5 - eye).length();
+                length > pixels * pixel_angle * distance
+            }
+        }
+    }
+}
+
+/// Triangles covering `triangle` once the edges with a midpoint are split.
+/// `midpoints[i]` is the midpoint of the edge from vertex `i` to `i + 1`.
+fn split_triangle(triangle: [usize; 3], midpoints: [Option<usize>; 3]) -> Vec<[usize; 3]> {
+    let split = midpoints.iter().filter(|midpoint| midpoint.is_some()).count();
+    // rotate the corners so the cases below only handle one layout
+    let rotation = match split {
+        1 => midpoints.iter().position(|midpoint| midpoint.is_some()).unwrap(),
+        2 => (midpoints.iter().position(|midpoint| midpoint.is_none()).unwrap() + 1) % 3,
+        _ => 0
+    };
+    let corner = |i: usize| triangle[(rotation + i) % 3];
+    let midpoint = |i: usize| midpoints[(rotation + i) % 3].unwrap();
+    let (a, b, c) = (corner(0), corner(1), corner(2));
+
+    match split {
+        0 => vec![triangle],
+        1 => vec![[a, midpoint(0), c], [midpoint(0), b, c]],
+        2 => vec![[midpoint(0), b, midpoint(1)], [a, midpoint(0), midpoint(1)], [a, midpoint(1), c]],
+        _ => vec![
+            [a, midpoint(0), midpoint(2)],
+            [midpoint(0), b, midpoint(1)],
+            [midpoint(2), midpoint(1), c],
+            [midpoint(0), midpoint(1), midpoint(2)]
+        ]
+    }
+}
+
+impl Mesh {
+    /// Tessellates the mesh until its edges meet `tessellation`, or after
+    /// `MAX_SUBDIVISIONS` passes, then moves every vertex by `displacement`
+    /// and recomputes the normals and the BVH. Split decisions only depend
+    /// on the edge, so neighbouring triangles share their new vertices and
+    /// no cracks open, except along seams where vertices are duplicated.
+    pub fn displaced(self, displacement: &Displacement, tessellation: Tessellation) -> Mesh {
+        let Mesh { mut positions, mut normals, mut uvs, mut triangles, material, transform_matrix, name, visibility, .. } = self;
+        let to_world = |point: Point3| match &transform_matrix {
+            Some(transform_matrix) => point.transform(&transform_matrix.mat),
+            None => point
+        };
+
+        for _ in 0..MAX_SUBDIVISIONS {
+            let world: Vec<Point3> = positions.iter().map(|&point| to_world(point)).collect();
+            let mut edges: HashMap<(usize, usize), Option<usize>> = HashMap::new();
+            let mut subdivided = Vec::with_capacity(triangles.len());
+
+            for &triangle in &triangles {
+                let mut midpoints = [None; 3];
+
+                for (i, midpoint) in midpoints.iter_mut().enumerate() {
+                    let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+
+                    *midpoint = *edges.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                        if !tessellation.splits(world[a], world[b]) {
+                            return None;
+                        }
+
+                        positions.push((positions[a] + positions[b]) * 0.5);
+                        let normal = normals[a] + normals[b];
+                        normals.push(if normal.length_squared() > 1e-24 { normal.normalized() } else { normals[a] });
+                        uvs.push(((uvs[a].0 + uvs[b].0) * 0.5, (uvs[a].1 + uvs[b].1) * 0.5));
+                        Some(positions.len() - 1)
+                    });
+                }
+
+                subdivided.extend(split_triangle(triangle, midpoints));
+            }
+
+            if subdivided.len() == triangles.len() {
+                break;
+            }
+
+            triangles = subdivided;
+        }
+
+        for i in 0..positions.len() {
+            let record = HitRecord {
+                t_min: 0.0,
+                point: to_world(positions[i]),
+                point_error: Vec3::new(0.0, 0.0, 0.0, false),
+                object_point: positions[i],
+                normal: normals[i],
+                geometric_normal: normals[i],
+                uv: uvs[i],
+                dpdu: Vec3::new(0.0, 0.0, 0.0, false),
+                dpdv: Vec3::new(0.0, 0.0, 0.0, false),
+                dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+                dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+                duvdx: (0.0, 0.0),
+                duvdy: (0.0, 0.0),
+                front_face: true,
+                material: &material,
+                name: ""
+            };
+
+            let offset = match displacement {
+                Displacement::Scalar { texture, scale } => normals[i] * (scale * texture.value(&record, normals[i]).x()),
+                Displacement::Vector { texture, scale } => texture.value(&record, normals[i]) * *scale
+            };
+
+            positions[i] = positions[i] + offset;
+        }
+
+        let mut mesh = Mesh::new(positions, Vec::new(), uvs, triangles, material, transform_matrix);
+        mesh.name = name;
+        mesh.visibility = visibility;
+
+        mesh
+    }
+}
@@ -0,0 +1,93 @@
+/// Piecewise constant distribution over `[0, 1)`, proportional to a list of
+/// non-negative weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    weights: Vec<f64>,
+    cdf: Vec<f64>,
+    pub integral: f64
+}
+
+impl Distribution1D {
+    /// A distribution with only zero weights is uniform.
+    pub fn new(weights: &[f64]) -> Distribution1D {
+        assert!(!weights.is_empty(), "Distribution needs at least one weight");
+
+        let count = weights.len() as f64;
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+
+        for weight in weights {
+            cdf.push(cdf.last().unwrap() + weight.max(0.0) / count);
+        }
+
+        let integral = *cdf.last().unwrap();
+
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0.0 { *value / integral } else { i as f64 / count };
+        }
+
+        Distribution1D { weights: weights.iter().map(|weight| weight.max(0.0)).collect(), cdf, integral }
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    /// Maps `u` in `[0, 1)` to a point of the domain, returning the point,
+    /// its density and the index of the segment it falls in.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        // last segment whose start is not past u, skipping empty segments
+        let index = self.cdf.partition_point(|&value| value <= u).clamp(1, self.len()) - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 { ((u - self.cdf[index]) / width).clamp(0.0, 1.0) } else { 0.5 };
+
+        ((index as f64 + offset) / self.len() as f64, self.pdf_at(index), index)
+    }
+
+    /// Density at a point of `[0, 1)`.
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.pdf_at(((x * self.len() as f64) as usize).min(self.len() - 1))
+    }
+
+    fn pdf_at(&self, index: usize) -> f64 {
+        if self.integral > 0.0 { self.weights[index] / self.integral } else { 1.0 }
+    }
+}
+
+/// Piecewise constant distribution over `[0, 1)²`, sampled by picking a
+/// row from the marginal distribution then a column within it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D
+}
+
+impl Distribution2D {
+    /// `weights` holds `height` rows of `width` weights.
+    pub fn new(weights: &[f64], width: usize, height: usize) -> Distribution2D {
+        assert_eq!(weights.len(), width * height, "Distribution has unexpected number of weights");
+
+        let rows: Vec<Distribution1D> = weights.chunks_exact(width).map(Distribution1D::new).collect();
+        let marginal = Distribution1D::new(&rows.iter().map(|row| row.integral).collect::<Vec<_>>());
+
+        Distribution2D { rows, marginal }
+    }
+
+    /// Point as `(x, y)`, with its density.
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample(u.1);
+        let (x, pdf_x, _) = self.rows[row].sample(u.0);
+
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, point: (f64, f64)) -> f64 {
+        let row = ((point.1 * self.rows.len() as f64) as usize).min(self.rows.len() - 1);
+
+        self.marginal.pdf(point.1) * self.rows[row].pdf(point.0)
+    }
+}
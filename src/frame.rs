@@ -0,0 +1,58 @@
+use crate::vec3::Vec3;
+
+/// Orthonormal shading frame. Local coordinates follow the convention of the
+/// microfacet functions in `brdf`: `x` along the tangent, `y` along the
+/// normal and `z` along the bitangent.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub tangent: Vec3,
+    pub normal: Vec3,
+    pub bitangent: Vec3
+}
+
+impl Frame {
+    pub fn new(normal: Vec3, tangent: Vec3) -> Frame {
+        let normal = Vec3::new(normal.x(), normal.y(), normal.z(), false).normalized();
+        let tangent = tangent - normal * normal.dot(tangent);
+
+        if tangent.length_squared() < 1e-12 {
+            return Frame::from_normal(normal);
+        }
+
+        let tangent = Vec3::new(tangent.x(), tangent.y(), tangent.z(), false).normalized();
+        let bitangent = tangent.cross(normal);
+
+        Frame { tangent, normal, bitangent }
+    }
+
+    pub fn from_normal(normal: Vec3) -> Frame {
+        let normal = Vec3::new(normal.x(), normal.y(), normal.z(), false).normalized();
+        let (tangent, _) = normal.orthonormal_basis();
+
+        Frame { tangent, normal, bitangent: tangent.cross(normal) }
+    }
+
+    /// Rotates the tangent around the normal by `theta` degrees.
+    pub fn rotated(&self, theta: f64) -> Frame {
+        if theta == 0.0 {
+            return *self;
+        }
+
+        let theta_rad = theta.to_radians();
+        let tangent = self.tangent * theta_rad.cos() + self.bitangent * theta_rad.sin();
+
+        Frame {
+            tangent,
+            normal: self.normal,
+            bitangent: tangent.cross(self.normal)
+        }
+    }
+
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(v.dot(self.tangent), v.dot(self.normal), v.dot(self.bitangent), false)
+    }
+
+    pub fn to_world(&self, v: Vec3) -> Vec3 {
+        v.x() * self.tangent + v.y() * self.normal + v.z() * self.bitangent
+    }
+}
@@ -1,19 +1,184 @@
 use crate::vec3::Vec3;
 use crate::ray::Ray;
 use crate::material::Material;
-use crate::transform::TransformMatrix;
+use crate::transform::{Transform, TransformMatrix};
+use crate::frame::Frame;
+use crate::math::GAMMA_3;
 
 use Vec3 as Point3;
 
-pub struct HitRecord {
+/// Surface interaction. `normal` is the shading normal and may be perturbed
+/// by the material, while `geometric_normal` is the true normal of the
+/// surface; both face the side the ray came from. `point_error` bounds the
+/// absolute error of `point` along each axis, as computed by the object's
+/// intersection routine. `object_point` is the hit point before the
+/// object's transform is applied. `dpdx`/`dpdy` and
+/// `duvdx`/`duvdy` give the footprint of a pixel on the surface when the ray
+/// carries differentials, and are zero otherwise. `dpdu` and `dpdv` are the
+/// partial derivatives of the point with respect to the `uv` coordinates.
+/// `name` is the name of the object hit, empty for unnamed objects.
+#[derive(Clone, Copy)]
+pub struct HitRecord<'a> {
     pub t_min: f64,
     pub point: Point3,
+    pub point_error: Vec3,
+    pub object_point: Point3,
     pub normal: Vec3,
+    pub geometric_normal: Vec3,
+    pub uv: (f64, f64),
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    pub dpdx: Vec3,
+    pub dpdy: Vec3,
+    pub duvdx: (f64, f64),
+    pub duvdy: (f64, f64),
     pub front_face: bool,
-    pub material: Material,
+    pub material: &'a Material,
+    pub name: &'a str,
+}
+
+impl HitRecord<'_> {
+    /// Intersects the ray's differentials with the tangent plane at the hit
+    /// and expresses the offsets in uv coordinates.
+    pub fn compute_differentials(&mut self, ray: &Ray) {
+        let differentials = match ray.differentials() {
+            Some(differentials) => differentials,
+            None => return
+        };
+
+        let normal = self.geometric_normal;
+        let d = normal.dot(self.point);
+        let rx_denominator = normal.dot(differentials.rx_direction);
+        let ry_denominator = normal.dot(differentials.ry_direction);
+
+        if rx_denominator.abs() < 1e-12 || ry_denominator.abs() < 1e-12 {
+            return;
+        }
+
+        let tx = (d - normal.dot(differentials.rx_origin)) / rx_denominator;
+        let ty = (d - normal.dot(differentials.ry_origin)) / ry_denominator;
+        self.dpdx = differentials.rx_origin + differentials.rx_direction * tx - self.point;
+        self.dpdy = differentials.ry_origin + differentials.ry_direction * ty - self.point;
+
+        // least squares solution of dp = dpdu du + dpdv dv, projected onto
+        // the two axes the normal is least aligned with
+        let (a0, a1) = if normal.x().abs() > normal.y().abs() && normal.x().abs() > normal.z().abs() {
+            (1, 2)
+        } else if normal.y().abs() > normal.z().abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+
+        let determinant = self.dpdu[a0] * self.dpdv[a1] - self.dpdv[a0] * self.dpdu[a1];
+
+        if determinant.abs() < 1e-12 {
+            return;
+        }
+
+        let solve = |dp: Vec3| (
+            (self.dpdv[a1] * dp[a0] - self.dpdv[a0] * dp[a1]) / determinant,
+            (self.dpdu[a0] * dp[a1] - self.dpdu[a1] * dp[a0]) / determinant
+        );
+
+        self.duvdx = solve(self.dpdx);
+        self.duvdy = solve(self.dpdy);
+    }
+
+    /// Moves the point to the space `matrix` transforms to, growing its
+    /// error bound by the rounding of the transform.
+    pub fn transform_point(&mut self, matrix: &[[f64; 4]; 4]) {
+        let (p, e) = (self.point, self.point_error);
+        let bound = |row: &[f64; 4]| {
+            let rounding = GAMMA_3 * ((row[0] * p.x()).abs() + (row[1] * p.y()).abs() + (row[2] * p.z()).abs() + row[3].abs());
+            let propagated = (1.0 + GAMMA_3) * (row[0].abs() * e.x() + row[1].abs() * e.y() + row[2].abs() * e.z());
+
+            rounding + propagated
+        };
+
+        self.point = p.transform(matrix);
+        self.point_error = Vec3::new(bound(&matrix[0]), bound(&matrix[1]), bound(&matrix[2]), false);
+    }
+
+    /// Origin for rays leaving the surface towards `direction`: the point
+    /// pushed along the geometric normal, to the side the ray leaves
+    /// through, just past the box bounding its error, so rays do not hit
+    /// the surface they leave.
+    pub fn spawn_origin(&self, direction: Vec3) -> Point3 {
+        let (n, e) = (self.geometric_normal, self.point_error);
+        let distance = n.x().abs() * e.x() + n.y().abs() * e.y() + n.z().abs() * e.z();
+        let offset = if direction.dot(n) >= 0.0 { n * distance } else { n * -distance };
+
+        // rounded away from the surface, so the sum cannot fall back into it
+        let away = |p: f64, offset: f64| {
+            let moved = p + offset;
+
+            if offset > 0.0 { moved.next_up() } else if offset < 0.0 { moved.next_down() } else { moved }
+        };
+
+        Point3::new(
+            away(self.point.x(), offset.x()),
+            away(self.point.y(), offset.y()),
+            away(self.point.z(), offset.z()),
+            true
+        )
+    }
+
+    /// Shading frame around the normal, with the tangent following `dpdu`
+    /// rotated by the material's anisotropy rotation.
+    pub fn frame(&self) -> Frame {
+        Frame::new(self.normal, self.dpdu).rotated(self.material.anisotropy_rotation)
+    }
+}
+
+/// Kinds of rays traced through the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayKind {
+    Camera,
+    Shadow,
+    /// Glossy or mirror reflection and refraction.
+    Reflection,
+    /// Diffuse bounces.
+    Indirect
+}
+
+/// Kinds of rays an object is visible to. Rays of other kinds go through
+/// the object as if it were not there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Visibility {
+    pub camera: bool,
+    pub shadow: bool,
+    pub reflection: bool,
+    pub indirect: bool
+}
+
+impl Visibility {
+    pub fn is_visible(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Shadow => self.shadow,
+            RayKind::Reflection => self.reflection,
+            RayKind::Indirect => self.indirect
+        }
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Visibility {
+        Visibility { camera: true, shadow: true, reflection: true, indirect: true }
+    }
 }
 
 pub trait Hit {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    /// Whether the ray hits the object within `[t_min, t_max]`. Unlike
+    /// `hit`, any intersection will do, not only the closest one.
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit(ray, t_min, t_max).is_some()
+    }
+
     fn transform_matrix(&self) -> Option<&TransformMatrix>;
+    fn name(&self) -> &str;
+    fn visibility(&self) -> Visibility;
 }
@@ -0,0 +1,163 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Candela distribution of a fixture read from an IES LM-63 file, using
+/// type C photometry. Vertical angles start at the nadir (0) and end at the
+/// zenith (180), horizontal angles turn around the vertical axis. `candela`
+/// holds one row of vertical samples per horizontal angle, with the file's
+/// multipliers applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    pub vertical_angles: Vec<f64>,
+    pub horizontal_angles: Vec<f64>,
+    pub candela: Vec<Vec<f64>>
+}
+
+/// Index of the interval of `angles` containing `angle` and the position
+/// within it, clamping to the ends.
+fn locate(angles: &[f64], angle: f64) -> (usize, f64) {
+    if angles.len() == 1 || angle <= angles[0] {
+        return (0, 0.0);
+    }
+
+    let i = angles.partition_point(|&a| a <= angle).min(angles.len() - 1) - 1;
+    let span = angles[i + 1] - angles[i];
+
+    (i, if span > 0.0 { ((angle - angles[i]) / span).clamp(0.0, 1.0) } else { 0.0 })
+}
+
+/// Number of entries announced by the file, which must be a whole number
+/// small enough to count.
+fn count(value: f64) -> io::Result<usize> {
+    if value < 0.0 || value.fract() != 0.0 || value >= u32::MAX as f64 {
+        return Err(invalid("IES file has invalid count"));
+    }
+
+    Ok(value as usize)
+}
+
+impl IesProfile {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<IesProfile> {
+        IesProfile::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the text of an IES file. Keyword lines before `TILT=` are
+    /// ignored, as are the lamp tilt factors.
+    pub fn parse(source: &str) -> io::Result<IesProfile> {
+        let mut lines = source.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => break line.trim()[5..].to_string(),
+                Some(_) => continue,
+                None => return Err(invalid("IES file has no TILT line"))
+            }
+        };
+
+        let values = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse::<f64>().map_err(|_| invalid("IES file has invalid number")))
+            .collect::<io::Result<Vec<f64>>>()?;
+        let mut values = values.into_iter();
+        let mut next = || values.next().ok_or_else(|| invalid("IES file is truncated"));
+
+        if tilt == "INCLUDE" {
+            // lamp to luminaire geometry, then angle and factor pairs
+            next()?;
+            let values_count = count(next()?)?.checked_mul(2).ok_or_else(|| invalid("IES file has invalid count"))?;
+
+            for _ in 0..values_count {
+                next()?;
+            }
+        }
+
+        let (_lamps, _lumens, multiplier) = (next()?, next()?, next()?);
+        let (vertical_count, horizontal_count) = (count(next()?)?, count(next()?)?);
+        let photometric_type = next()?;
+        let (_units, _width, _length, _height) = (next()?, next()?, next()?, next()?);
+        let (ballast_factor, _ballast_lamp_factor, _watts) = (next()?, next()?, next()?);
+
+        if photometric_type != 1.0 {
+            return Err(invalid("IES file uses unsupported type A or B photometry"));
+        }
+
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid("IES file has no angles"));
+        }
+
+        let vertical_angles = (0..vertical_count).map(|_| next()).collect::<io::Result<Vec<f64>>>()?;
+        let horizontal_angles = (0..horizontal_count).map(|_| next()).collect::<io::Result<Vec<f64>>>()?;
+        let candela = (0..horizontal_count)
+            .map(|_| (0..vertical_count).map(|_| next().map(|value| value * multiplier * ballast_factor)).collect())
+            .collect::<io::Result<Vec<Vec<f64>>>>()?;
+
+        let sorted = |angles: &[f64]| angles.windows(2).all(|pair| pair[0] <= pair[1]);
+
+        if !sorted(&vertical_angles) || !sorted(&horizontal_angles) {
+            return Err(invalid("IES file has unsorted angles"));
+        }
+
+        Ok(IesProfile { vertical_angles, horizontal_angles, candela })
+    }
+
+    /// Horizontal angle folded into the range covered by the file, which
+    /// may only describe a quadrant or a half of a symmetric fixture. Halves
+    /// run either from 0 to 180 or from 90 to 270.
+    fn fold_horizontal(&self, angle: f64) -> f64 {
+        let angle = angle.rem_euclid(360.0);
+        let (first, last) = (self.horizontal_angles[0], *self.horizontal_angles.last().unwrap());
+        let half = if angle > 180.0 { 360.0 - angle } else { angle };
+
+        if last == 0.0 {
+            0.0
+        } else if first == 90.0 && last == 270.0 {
+            // mirrored about the plane through 90 and 270
+            if (90.0..=270.0).contains(&angle) { angle } else { (180.0 - angle).rem_euclid(360.0) }
+        } else if last == 90.0 {
+            if half > 90.0 { 180.0 - half } else { half }
+        } else if last == 180.0 {
+            half
+        } else {
+            angle
+        }
+    }
+
+    /// Intensity in candela, bilinearly interpolated. Directions outside
+    /// the measured vertical range receive no light.
+    pub fn candela(&self, vertical: f64, horizontal: f64) -> f64 {
+        let (first, last) = (self.vertical_angles[0], *self.vertical_angles.last().unwrap());
+
+        if vertical < first - 1e-9 || vertical > last + 1e-9 {
+            return 0.0;
+        }
+
+        let (v, tv) = locate(&self.vertical_angles, vertical);
+        let (h, th) = locate(&self.horizontal_angles, self.fold_horizontal(horizontal));
+        let v1 = (v + 1).min(self.vertical_angles.len() - 1);
+        let h1 = (h + 1).min(self.horizontal_angles.len() - 1);
+        let row = |h: usize| self.candela[h][v] * (1.0 - tv) + self.candela[h][v1] * tv;
+
+        row(h) * (1.0 - th) + row(h1) * th
+    }
+
+    /// Intensity towards a direction in the fixture's frame, where the
+    /// nadir is `-y` and horizontal angles turn from `+x` towards `-z`.
+    pub fn intensity(&self, direction: Vec3) -> f64 {
+        let direction = direction.normalized();
+        let vertical = (-direction.y()).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = (-direction.z()).atan2(direction.x()).to_degrees();
+
+        self.candela(vertical, horizontal)
+    }
+
+    pub fn max_candela(&self) -> f64 {
+        self.candela.iter().flatten().fold(0.0, |max, &value| value.max(max))
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::vec3::Vec3;
+
+use Vec3 as Color;
+
+/// Encoding of the values stored in an image file. Color maps are usually
+/// authored in sRGB, while data maps such as roughness are linear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Srgb,
+    Linear
+}
+
+/// Linear RGB image stored row by row, starting with the top row. Its MIP
+/// pyramid is built on first use.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+    pyramid: OnceLock<Vec<Image>>
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Number of values in a `width` by `height` image with `values` values per
+/// pixel, for sizes read from a file header. Empty images and sizes that do
+/// not fit in memory are rejected.
+fn value_count(width: usize, height: usize, values: usize) -> io::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(invalid("image is empty"));
+    }
+
+    width.checked_mul(height)
+        .and_then(|count| count.checked_mul(values))
+        .ok_or_else(|| invalid("image is too large"))
+}
+
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Image {
+        assert_eq!(pixels.len(), width * height, "Image has unexpected number of pixels");
+
+        Image { width, height, pixels, pyramid: OnceLock::new() }
+    }
+
+    /// Loads a PPM, PFM, Radiance HDR or PNG file, picking the format from
+    /// its contents. `color_space` only applies to integer formats, PFM and
+    /// HDR are always linear.
+    pub fn load<P: AsRef<Path>>(path: P, color_space: ColorSpace) -> io::Result<Image> {
+        Image::from_bytes(&fs::read(path)?, color_space)
+    }
+
+    pub fn from_bytes(bytes: &[u8], color_space: ColorSpace) -> io::Result<Image> {
+        match bytes {
+            [0x89, b'P', b'N', b'G', ..] => Image::from_png(bytes, color_space),
+            [b'P', b'F' | b'f', ..] => Image::from_pfm(bytes),
+            [b'P', b'3' | b'6', ..] => Image::from_ppm(bytes, color_space),
+            [b'#', b'?', ..] => Image::from_hdr(bytes),
+            _ => Err(invalid("unsupported image format"))
+        }
+    }
+
+    /// Parses an ASCII (`P3`) or binary (`P6`) portable pixmap.
+    pub fn from_ppm(bytes: &[u8], color_space: ColorSpace) -> io::Result<Image> {
+        let mut header = Header::new(bytes);
+        let magic = header.token()?;
+        let width = header.number()?;
+        let height = header.number()?;
+        let max_value = header.number()?;
+
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("PPM file has invalid maximum value"));
+        }
+
+        let count = value_count(width, height, 3)?;
+        let samples: Vec<usize> = if magic == "P3" {
+            (0..count).map(|_| header.number()).collect::<io::Result<_>>()?
+        } else {
+            let data = header.data();
+            let size = if max_value > 255 { 2 } else { 1 };
+
+            if data.len() / size < count {
+                return Err(invalid("PPM file is truncated"));
+            }
+
+            data.chunks_exact(size)
+                .take(count)
+                .map(|chunk| chunk.iter().fold(0, |value, byte| (value << 8) | *byte as usize))
+                .collect()
+        };
+
+        let scale = 1.0 / max_value as f64;
+
+        Ok(Image::from_samples(width, height, &samples, 3, scale, color_space))
+    }
+
+    /// Parses a portable float map, in color (`PF`) or grayscale (`Pf`).
+    /// The sign of the scale gives the byte order and rows are stored from
+    /// the bottom up.
+    pub fn from_pfm(bytes: &[u8]) -> io::Result<Image> {
+        let mut header = Header::new(bytes);
+        let channels = if header.token()? == "PF" { 3 } else { 1 };
+        let width = header.number()?;
+        let height = header.number()?;
+        let scale: f64 = header.token()?.parse().map_err(|_| invalid("PFM file has invalid scale"))?;
+        let data = header.data();
+        let count = value_count(width, height, channels)?;
+
+        if data.len() / 4 < count {
+            return Err(invalid("PFM file is truncated"));
+        }
+
+        let values: Vec<f64> = data.chunks_exact(4)
+            .take(count)
+            .map(|chunk| {
+                let bytes = chunk.try_into().unwrap();
+                (if scale < 0.0 { f32::from_le_bytes(bytes) } else { f32::from_be_bytes(bytes) }) as f64
+            })
+            .collect();
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let pixel = &values[(y * width + x) * channels..(y * width + x + 1) * channels];
+                pixels.push(Color::new(pixel[0], pixel[channels / 2], pixel[channels - 1], false));
+            }
+        }
+
+        Ok(Image::new(width, height, pixels))
+    }
+
+    /// Parses a Radiance RGBE file, flat or run-length encoded, with rows
+    /// stored from the top down (`-Y height +X width`).
+    pub fn from_hdr(bytes: &[u8]) -> io::Result<Image> {
+        let mut lines = bytes.split(|&byte| byte == b'\n');
+        let mut offset = 0;
+        let mut next_line = || {
+            let line = lines.next().ok_or_else(|| invalid("HDR file is truncated"))?;
+            offset += line.len() + 1;
+            Ok::<_, io::Error>(String::from_utf8_lossy(line).trim().to_string())
+        };
+
+        // header variables end with an empty line
+        loop {
+            let line = next_line()?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid("HDR file has unsupported format"));
+            }
+        }
+
+        let resolution = next_line()?;
+        let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (
+                width.parse().map_err(|_| invalid("HDR file has invalid width"))?,
+                height.parse().map_err(|_| invalid("HDR file has invalid height"))?
+            ),
+            _ => return Err(invalid("HDR file has unsupported orientation"))
+        };
+
+        let data = &bytes[offset.min(bytes.len())..];
+
+        // a run covers at most 127 pixels of a channel with two bytes, so no
+        // pixel takes less than a sixteenth of a byte
+        let count = value_count(width, height, 1)?;
+
+        if count / 16 > data.len() {
+            return Err(invalid("HDR file is truncated"));
+        }
+
+        let mut position = 0;
+        let mut byte = || {
+            let value = data.get(position).copied().ok_or_else(|| invalid("HDR file is truncated"));
+            position += 1;
+            value
+        };
+        let mut pixels = Vec::with_capacity(count);
+        let mut scanline = vec![[0u8; 4]; width];
+
+        for _ in 0..height {
+            let start = [byte()?, byte()?, byte()?, byte()?];
+
+            if (8..0x8000).contains(&width) && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0 {
+                if ((start[2] as usize) << 8 | start[3] as usize) != width {
+                    return Err(invalid("HDR file has invalid scanline width"));
+                }
+
+                // each channel is stored separately as runs and literal spans
+                for channel in 0..4 {
+                    let mut x = 0;
+
+                    while x < width {
+                        let count = byte()? as usize;
+                        let (count, run) = if count > 128 { (count - 128, true) } else { (count, false) };
+
+                        if count == 0 || x + count > width {
+                            return Err(invalid("HDR file has invalid run length"));
+                        }
+
+                        let value = if run { byte()? } else { 0 };
+
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = if run { value } else { byte()? };
+                        }
+
+                        x += count;
+                    }
+                }
+            } else {
+                scanline[0] = start;
+
+                for pixel in &mut scanline[1..] {
+                    *pixel = [byte()?, byte()?, byte()?, byte()?];
+                }
+            }
+
+            pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+                if e == 0 {
+                    Color::new(0.0, 0.0, 0.0, false)
+                } else {
+                    let scale = 2.0_f64.powi(e as i32 - 136);
+                    Color::new(r as f64 * scale, g as f64 * scale, b as f64 * scale, false)
+                }
+            }));
+        }
+
+        Ok(Image::new(width, height, pixels))
+    }
+
+    pub fn from_png(bytes: &[u8], color_space: ColorSpace) -> io::Result<Image> {
+        let mut decoder = png::Decoder::new(bytes);
+        // palettes and low bit depths are expanded to 8 bits per sample
+        decoder.set_transformations(png::Transformations::EXPAND);
+
+        let mut reader = decoder.read_info().map_err(|err| invalid(&err.to_string()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|err| invalid(&err.to_string()))?;
+
+        let channels = info.color_type.samples();
+        let (samples, scale): (Vec<usize>, f64) = match info.bit_depth {
+            png::BitDepth::Sixteen => (
+                buffer[..info.buffer_size()].chunks_exact(2).map(|chunk| ((chunk[0] as usize) << 8) | chunk[1] as usize).collect(),
+                1.0 / 65535.0
+            ),
+            _ => (buffer[..info.buffer_size()].iter().map(|byte| *byte as usize).collect(), 1.0 / 255.0)
+        };
+
+        Ok(Image::from_samples(info.width as usize, info.height as usize, &samples, channels, scale, color_space))
+    }
+
+    /// Builds an image from interleaved integer samples. Grayscale images
+    /// have one or two channels and alpha is ignored.
+    fn from_samples(width: usize, height: usize, samples: &[usize], channels: usize, scale: f64, color_space: ColorSpace) -> Image {
+        let decode = |sample: usize| {
+            let value = sample as f64 * scale;
+
+            match color_space {
+                ColorSpace::Srgb => srgb_to_linear(value),
+                ColorSpace::Linear => value
+            }
+        };
+
+        let pixels = samples.chunks_exact(channels)
+            .take(width * height)
+            .map(|pixel| {
+                if channels < 3 {
+                    let gray = decode(pixel[0]);
+                    Color::new(gray, gray, gray, false)
+                } else {
+                    Color::new(decode(pixel[0]), decode(pixel[1]), decode(pixel[2]), false)
+                }
+            })
+            .collect();
+
+        Image::new(width, height, pixels)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Image at half the resolution, each pixel averaging a 2x2 block.
+    /// Odd rows and columns are folded into the last block.
+    fn downsampled(&self) -> Image {
+        let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+        let span = |i: usize, size: usize, source_size: usize| 2 * i..if i + 1 == size { source_size } else { 2 * i + 2 };
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let xs = span(x, width, self.width);
+                let ys = span(y, height, self.height);
+                let count = (xs.len() * ys.len()) as f64;
+                let mut sum = Color::new(0.0, 0.0, 0.0, false);
+
+                for sy in ys {
+                    for sx in xs.clone() {
+                        sum = sum + self.pixel(sx, sy);
+                    }
+                }
+
+                pixels.push(sum / count);
+            }
+        }
+
+        Image::new(width, height, pixels)
+    }
+
+    /// Number of MIP levels, including the image itself.
+    pub fn levels(&self) -> usize {
+        self.pyramid().len() + 1
+    }
+
+    /// MIP level `level`, 0 being the image itself and each following level
+    /// halving the resolution down to a single pixel.
+    pub fn level(&self, level: usize) -> &Image {
+        let pyramid = self.pyramid();
+
+        match level.min(pyramid.len()) {
+            0 => self,
+            level => &pyramid[level - 1]
+        }
+    }
+
+    fn pyramid(&self) -> &Vec<Image> {
+        self.pyramid.get_or_init(|| {
+            let mut levels: Vec<Image> = Vec::new();
+
+            while levels.last().unwrap_or(self).width > 1 || levels.last().unwrap_or(self).height > 1 {
+                let next = levels.last().unwrap_or(self).downsampled();
+                levels.push(next);
+            }
+
+            levels
+        })
+    }
+}
+
+impl PartialEq for Image {
+    /// Images are compared by identity, they are shared rather than copied.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+/// Whitespace separated header of the netpbm family of formats, where `#`
+/// starts a comment running to the end of the line.
+struct Header<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl<'a> Header<'a> {
+    fn new(bytes: &'a [u8]) -> Header<'a> {
+        Header { bytes, position: 0 }
+    }
+
+    fn token(&mut self) -> io::Result<String> {
+        while self.position < self.bytes.len() {
+            match self.bytes[self.position] {
+                b'#' => {
+                    while self.position < self.bytes.len() && self.bytes[self.position] != b'\n' {
+                        self.position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break
+            }
+        }
+
+        let start = self.position;
+
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return Err(invalid("image header is truncated"));
+        }
+
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.position]).into_owned())
+    }
+
+    fn number(&mut self) -> io::Result<usize> {
+        self.token()?.parse().map_err(|_| invalid("image header has invalid number"))
+    }
+
+    /// Binary payload, which starts after the single whitespace character
+    /// ending the header.
+    fn data(&self) -> &'a [u8] {
+        &self.bytes[(self.position + 1).min(self.bytes.len())..]
+    }
+}
@@ -4,10 +4,32 @@ pub mod ray;
 pub mod hit;
 pub mod sphere;
 pub mod box3;
+pub mod aabb;
+pub mod mesh;
+pub mod displacement;
 pub mod transform;
 pub mod camera;
 pub mod progressbar;
 pub mod brdf;
+pub mod multiscatter;
+pub mod frame;
 pub mod render;
 pub mod material;
+pub mod image;
+pub mod texture;
+pub mod noise;
+pub mod procedural;
+pub mod shader;
+pub mod bump;
+pub mod subsurface;
+pub mod thinfilm;
+pub mod measured;
+pub mod preset;
+pub mod medium;
 pub mod light;
+pub mod lighttree;
+pub mod ies;
+pub mod distribution;
+pub mod background;
+pub mod sky;
+pub mod scene;
@@ -1,14 +1,81 @@
 use rand::Rng;
 
 use crate::vec3::Vec3;
+use crate::ies::IesProfile;
+use crate::texture::ImageTexture;
+use crate::transform::{Transform, TransformMatrix};
 
 use Vec3 as Point3;
 use Vec3 as Color;
 
+/// Slide projected along `-z` through a frustum of vertical field of view
+/// `vfov`, in degrees. The top of the image is towards `+y`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projector {
+    pub vfov: f64,
+    pub aspect_ratio: f64,
+    pub gobo: ImageTexture
+}
+
+impl Projector {
+    pub fn new(vfov: f64, aspect_ratio: f64, gobo: ImageTexture) -> Projector {
+        Projector { vfov, aspect_ratio, gobo }
+    }
+
+    /// Color of the slide seen along a direction in the projector's frame,
+    /// black outside the frustum.
+    pub fn filter(&self, direction: Vec3) -> Color {
+        if direction.z() >= 0.0 {
+            return Color::new(0.0, 0.0, 0.0, false);
+        }
+
+        let half_height = (self.vfov.to_radians() / 2.0).tan();
+        let half_width = self.aspect_ratio * half_height;
+        let x = direction.x() / -direction.z();
+        let y = direction.y() / -direction.z();
+
+        if x.abs() > half_width || y.abs() > half_height {
+            return Color::new(0.0, 0.0, 0.0, false);
+        }
+
+        self.gobo.sample((0.5 + 0.5 * x / half_width, 0.5 + 0.5 * y / half_height))
+    }
+}
+
+/// Objects affected by a light, identified by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Linking {
+    #[default]
+    All,
+    Include(Vec<String>),
+    Exclude(Vec<String>)
+}
+
+impl Linking {
+    pub fn includes(&self, name: &str) -> bool {
+        match self {
+            Linking::All => true,
+            Linking::Include(names) => names.iter().any(|included| included == name),
+            Linking::Exclude(names) => names.iter().all(|excluded| excluded != name)
+        }
+    }
+}
+
+/// Disk light facing `z`. Without a profile or projector it emits `color`
+/// uniformly. Otherwise `color` is modulated by the measured candela
+/// distribution of an IES profile and by the slide of a projector, both
+/// oriented by the rotation of `transform_matrix`, and falls off with the
+/// squared distance. `light_linking` selects the objects the light
+/// illuminates and `shadow_linking` the objects casting its shadows.
 pub struct Light {
     pub color: Color,
     pub position: Point3,
-    pub radius: f64
+    pub radius: f64,
+    pub profile: Option<IesProfile>,
+    pub projector: Option<Projector>,
+    pub transform_matrix: Option<TransformMatrix>,
+    pub light_linking: Linking,
+    pub shadow_linking: Linking
 }
 
 impl Light {
@@ -16,7 +83,12 @@ impl Light {
         Light {
             color,
             position,
-            radius
+            radius,
+            profile: None,
+            projector: None,
+            transform_matrix: None,
+            light_linking: Linking::All,
+            shadow_linking: Linking::All
         }
     }
 
@@ -30,4 +102,53 @@ impl Light {
 
         self.position + Vec3::new(x, y, 0.0, false)
     }
-}
\ No newline at end of file
+
+    /// Color emitted along `direction`, given in world space and pointing
+    /// away from the light.
+    pub fn emission(&self, direction: Vec3) -> Color {
+        if !self.is_directional() {
+            return self.color;
+        }
+
+        // only the rotation of the transform applies to directions
+        let direction = Vec3::new(direction.x(), direction.y(), direction.z(), false);
+        let local = match &self.transform_matrix {
+            Some(transform_matrix) => direction.transform(&transform_matrix.inv),
+            None => direction
+        };
+
+        let mut color = self.color;
+
+        if let Some(profile) = &self.profile {
+            color = color * profile.intensity(local);
+        }
+
+        if let Some(projector) = &self.projector {
+            color = color * projector.filter(local);
+        }
+
+        color
+    }
+
+    /// Whether the emission varies with the direction, which makes the light
+    /// physically based.
+    fn is_directional(&self) -> bool {
+        self.profile.is_some() || self.projector.is_some()
+    }
+
+    /// Light received at `point` with surface normal `normal` from the point
+    /// `sample` on the light. Uniform lights keep their historical model,
+    /// without falloff.
+    pub fn irradiance(&self, sample: Point3, point: Point3, normal: Vec3) -> Color {
+        let light_dir = sample - point;
+
+        if self.is_directional() {
+            let distance_squared = light_dir.length_squared();
+            let cos_theta = normal.dot(light_dir.normalized()).max(0.0);
+
+            self.emission(-light_dir) * (cos_theta / distance_squared)
+        } else {
+            self.color * normal.dot(light_dir.normalized()).max(0.0)
+        }
+    }
+}
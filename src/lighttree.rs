@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use crate::vec3::Vec3;
+use crate::aabb::Aabb;
+use crate::brdf::luminance;
+use crate::light::{Light, Linking};
+use crate::transform::Transform;
+
+use Vec3 as Point3;
+
+fn direction(v: Vec3) -> Vec3 {
+    Vec3::new(v.x(), v.y(), v.z(), false)
+}
+
+/// Bounds of the position, orientation and power of a group of lights.
+/// Every light of the group emits within `cos_theta_o` of `axis`, a cosine
+/// of -1 standing for all directions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    pub axis: Vec3,
+    pub cos_theta_o: f64,
+    pub power: f64
+}
+
+impl LightBounds {
+    pub fn from_light(light: &Light) -> LightBounds {
+        let extent = Vec3::new(light.radius, light.radius, 0.0, false);
+        let bounds = Aabb::new(light.position - extent, light.position + extent);
+        let mut power = luminance(light.color).max(0.0);
+        let mut axis = Vec3::new(0.0, 0.0, -1.0, false);
+        let mut cos_theta_o = -1.0;
+
+        if let Some(profile) = &light.profile {
+            power *= profile.max_candela();
+        }
+
+        if let Some(projector) = &light.projector {
+            let image = &projector.gobo.image;
+            let brightest = (0..image.height)
+                .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+                .fold(0.0, |max: f64, (x, y)| max.max(luminance(image.pixel(x, y))));
+            let half_height = (projector.vfov.to_radians() / 2.0).tan();
+            let half_width = projector.aspect_ratio * half_height;
+
+            power *= brightest;
+            // the frustum's corners are the farthest from its axis
+            cos_theta_o = 1.0 / (1.0 + half_width * half_width + half_height * half_height).sqrt();
+
+            if let Some(transform_matrix) = &light.transform_matrix {
+                axis = axis.transform(&transform_matrix.mat).normalized();
+            }
+        }
+
+        LightBounds { bounds, axis, cos_theta_o, power }
+    }
+
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        let (axis, cos_theta_o) = union_cone((self.axis, self.cos_theta_o), (other.axis, other.cos_theta_o));
+
+        LightBounds {
+            bounds: self.bounds.union(&other.bounds),
+            axis,
+            cos_theta_o,
+            power: self.power + other.power
+        }
+    }
+
+    /// Conservative estimate of the light reaching `point` on a surface
+    /// facing `normal`. It is only zero when no light of the group can
+    /// illuminate the point.
+    pub fn importance(&self, point: Point3, normal: Vec3) -> f64 {
+        if self.power <= 0.0 {
+            return 0.0;
+        }
+
+        let center = self.bounds.center();
+        let radius = direction(self.bounds.max - center).length();
+        let offset = direction(point - center);
+        let distance = offset.length();
+        // points inside the bounds are not told apart
+        let distance_squared = (distance * distance).max(radius * radius).max(1e-9);
+
+        if distance <= radius {
+            return self.power / distance_squared;
+        }
+
+        let to_point = offset / distance;
+        let theta_b = (radius / distance).asin();
+        let theta_w = self.axis.dot(to_point).clamp(-1.0, 1.0).acos();
+        let theta_o = self.cos_theta_o.clamp(-1.0, 1.0).acos();
+        let theta_i = normal.normalized().dot(-to_point).clamp(-1.0, 1.0).acos();
+
+        // lights emit nothing outside their cone, and receivers get nothing
+        // from below their surface
+        let incidence_angle = (theta_i - theta_b).max(0.0);
+
+        if theta_w - theta_b > theta_o || incidence_angle >= std::f64::consts::FRAC_PI_2 {
+            return 0.0;
+        }
+
+        self.power * incidence_angle.cos() / distance_squared
+    }
+}
+
+/// Smallest cone containing two cones, given by their axis and the cosine
+/// of their spread.
+fn union_cone(a: (Vec3, f64), b: (Vec3, f64)) -> (Vec3, f64) {
+    let pi = std::f64::consts::PI;
+
+    if a.1 <= -1.0 || b.1 <= -1.0 {
+        return (a.0, -1.0);
+    }
+
+    let theta_a = a.1.clamp(-1.0, 1.0).acos();
+    let theta_b = b.1.clamp(-1.0, 1.0).acos();
+    let theta_d = a.0.dot(b.0).clamp(-1.0, 1.0).acos();
+
+    if (theta_d + theta_b).min(pi) <= theta_a {
+        return a;
+    }
+
+    if (theta_d + theta_a).min(pi) <= theta_b {
+        return b;
+    }
+
+    let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+    let normal = a.0.cross(b.0);
+
+    if theta_o >= pi || normal.length_squared() == 0.0 {
+        return (a.0, -1.0);
+    }
+
+    // turn the first axis towards the second one
+    let normal = normal.normalized();
+    let theta_r = theta_o - theta_a;
+    let axis = a.0 * theta_r.cos() + normal.cross(a.0) * theta_r.sin();
+
+    (axis.normalized(), theta_o.cos())
+}
+
+/// Node of a hierarchy. Leaves hold the light at index `start`, inner
+/// nodes have their first child right after them and the second at `start`.
+#[derive(Debug, Clone)]
+struct LightNode {
+    bounds: LightBounds,
+    start: usize,
+    is_leaf: bool
+}
+
+/// Appends to `nodes` the node covering `entries`, splitting at the median
+/// center along the widest axis of the centers.
+fn build_node(nodes: &mut Vec<LightNode>, entries: &mut [(usize, LightBounds)]) -> LightBounds {
+    let index = nodes.len();
+
+    if entries.len() == 1 {
+        let (light, bounds) = entries[0];
+        nodes.push(LightNode { bounds, start: light, is_leaf: true });
+        return bounds;
+    }
+
+    nodes.push(LightNode { bounds: entries[0].1, start: 0, is_leaf: false });
+
+    let centers = entries.iter().fold(Aabb::empty(), |centers, (_, bounds)| centers.grow(bounds.bounds.center()));
+    let axis = centers.longest_axis();
+    let half = entries.len() / 2;
+
+    entries.select_nth_unstable_by(half, |a, b| a.1.bounds.center()[axis].total_cmp(&b.1.bounds.center()[axis]));
+
+    let (first, second) = entries.split_at_mut(half);
+    let first_bounds = build_node(nodes, first);
+    let second_index = nodes.len();
+    let bounds = first_bounds.union(&build_node(nodes, second));
+    nodes[index] = LightNode { bounds, start: second_index, is_leaf: false };
+
+    bounds
+}
+
+/// Lights of a scene kept in a bounding hierarchy, so a light can be picked
+/// according to its estimated contribution without visiting all of them.
+/// Receivers named in the lights' linking get a hierarchy of only the
+/// lights linked to them, receivers named nowhere share another one.
+pub struct LightTree {
+    lights: Vec<Light>,
+    hierarchies: Vec<Vec<LightNode>>,
+    linked: HashMap<String, usize>,
+    unnamed: usize
+}
+
+impl LightTree {
+    pub fn new(lights: Vec<Light>) -> LightTree {
+        let bounds: Vec<LightBounds> = lights.iter().map(LightBounds::from_light).collect();
+        let mut hierarchies = Vec::new();
+        // receivers linked to the same lights share a hierarchy
+        let mut subsets: HashMap<Vec<bool>, usize> = HashMap::new();
+        let mut hierarchy = |linked: Vec<bool>| {
+            *subsets.entry(linked).or_insert_with_key(|linked| {
+                let mut entries: Vec<(usize, LightBounds)> = bounds.iter().copied().enumerate().filter(|(i, _)| linked[*i]).collect();
+                let mut nodes = Vec::new();
+
+                if !entries.is_empty() {
+                    build_node(&mut nodes, &mut entries);
+                }
+
+                hierarchies.push(nodes);
+                hierarchies.len() - 1
+            })
+        };
+
+        hierarchy(vec![true; lights.len()]);
+        let unnamed = hierarchy(lights.iter().map(|light| !matches!(light.light_linking, Linking::Include(_))).collect());
+        let mut linked = HashMap::new();
+
+        for light in &lights {
+            if let Linking::Include(names) | Linking::Exclude(names) = &light.light_linking {
+                for name in names {
+                    if !linked.contains_key(name) {
+                        let index = hierarchy(lights.iter().map(|light| light.light_linking.includes(name)).collect());
+                        linked.insert(name.clone(), index);
+                    }
+                }
+            }
+        }
+
+        LightTree { lights, hierarchies, linked, unnamed }
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Picks a light for `point` on a surface facing `normal`, descending
+    /// the hierarchy towards the children of largest importance, and
+    /// returns it with the probability it had to be picked. Returns `None`
+    /// when no light can reach the point.
+    pub fn sample(&self, point: Point3, normal: Vec3, u: f64) -> Option<(&Light, f64)> {
+        self.sample_nodes(&self.hierarchies[0], point, normal, u)
+    }
+
+    /// `sample`, only picking among the lights whose light linking includes
+    /// the receiver named `receiver`.
+    pub fn sample_linked(&self, receiver: &str, point: Point3, normal: Vec3, u: f64) -> Option<(&Light, f64)> {
+        let index = self.linked.get(receiver).copied().unwrap_or(self.unnamed);
+
+        self.sample_nodes(&self.hierarchies[index], point, normal, u)
+    }
+
+    fn sample_nodes(&self, nodes: &[LightNode], point: Point3, normal: Vec3, u: f64) -> Option<(&Light, f64)> {
+        if nodes.is_empty() || nodes[0].bounds.importance(point, normal) <= 0.0 {
+            return None;
+        }
+
+        let mut u = u;
+        let mut pmf = 1.0;
+        let mut index = 0;
+
+        while !nodes[index].is_leaf {
+            let (first, second) = (index + 1, nodes[index].start);
+            let first_importance = nodes[first].bounds.importance(point, normal);
+            let second_importance = nodes[second].bounds.importance(point, normal);
+            let total = first_importance + second_importance;
+
+            if total <= 0.0 {
+                return None;
+            }
+
+            let probability = first_importance / total;
+
+            if u < probability {
+                u /= probability;
+                pmf *= probability;
+                index = first;
+            } else {
+                u = ((u - probability) / (1.0 - probability)).min(1.0 - f64::EPSILON);
+                pmf *= 1.0 - probability;
+                index = second;
+            }
+        }
+
+        Some((&self.lights[nodes[index].start], pmf))
+    }
+}
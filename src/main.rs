@@ -2,6 +2,7 @@ use std::env;
 use std::time;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::sync::Arc;
 
 use raytracer::math::{div_up, clamp};
 use raytracer::box3::Box3;
@@ -13,7 +14,9 @@ use raytracer::progressbar::ProgressBar;
 use raytracer::render::{trace_ray};
 use raytracer::material::Material;
 use raytracer::light::Light;
-use raytracer::transform::{translation_matrix, scaling_matrix, x_rotation_matrix, y_rotation_matrix};
+use raytracer::scene::Scene;
+use raytracer::background::{Background, EnvironmentMap};
+use raytracer::image::{Image, ColorSpace};
 
 use Vec3 as Point3;
 use Vec3 as Color;
@@ -23,7 +26,7 @@ fn main() {
     let args : Vec<String> = env::args().collect();
 
     if args.len() < 2 {
-        eprintln!("Usage: raytracer <out_path>");
+        eprintln!("Usage: raytracer <out_path> [environment_map]");
         std::process::exit(1)
     }
 
@@ -83,7 +86,7 @@ fn main() {
         0.35
     );
 
-    let wwall = Material::new(
+    let _wwall = Material::new(
         Color::new(0.41, 0.42, 0.43, false),
         0.5,
         0.1
@@ -92,14 +95,14 @@ fn main() {
     let bottom = Box3::new(
         Point3::new(-50.0, -6.0, -10.0, true),
         Point3::new(50.0, -5.0, 7.5, true),
-        rwall,
+        rwall.clone(),
         None
     );
 
     let left = Box3::new(
         Point3::new(-50.0, -5.0, -10.0, true),
         Point3::new(-8.75, 5.0, 7.5, true),
-        gwall,
+        gwall.clone(),
         None
     );
 
@@ -165,19 +168,20 @@ fn main() {
     // );
 
     // world
-    let mut world: Vec<Box<dyn Hit>> = Vec::new();
-    // world.push(Box::new(sphere));
-    // world.push(Box::new(sphere2));
-    // world.push(Box::new(cube));
-    // world.push(Box::new(small_cube));
-    // world.push(Box::new(ball));
-    // world.push(Box::new(big_ball));
-    world.push(Box::new(bottom));
-    world.push(Box::new(left));
-    world.push(Box::new(right));
-    world.push(Box::new(top));
-    world.push(Box::new(asphere));
-    world.push(Box::new(bsphere));
+    let world: Vec<Box<dyn Hit>> = vec![
+        // Box::new(sphere),
+        // Box::new(sphere2),
+        // Box::new(cube),
+        // Box::new(small_cube),
+        // Box::new(ball),
+        // Box::new(big_ball),
+        Box::new(bottom),
+        Box::new(left),
+        Box::new(right),
+        Box::new(top),
+        Box::new(asphere),
+        Box::new(bsphere),
+    ];
 
     // progress bar
     let length: usize = 50;
@@ -192,11 +196,23 @@ fn main() {
         0.2
     );
 
+    let mut scene = Scene::new(world, vec![light]);
+
+    // optional HDR environment lighting the scene
+    if let Some(environment_path) = args.get(2) {
+        let image = Image::load(environment_path, ColorSpace::Linear).expect("Unable to load environment map");
+        scene.background = Background::Environment(EnvironmentMap::new(Arc::new(image), 0.0, 1.0));
+    }
+
     // render
     let timer = time::Instant::now();
 
     println!("\nRendering started...\n");
 
+    // differentials span the distance between neighbouring samples
+    let du = 1.0 / f64::from((image_width - 1) * samples_per_pixel);
+    let dv = 1.0 / f64::from((image_height - 1) * samples_per_pixel);
+
     for y in (0..image_height).rev() {
         for x in 0..image_width {
             let mut pixel_color = Color::new(0.0, 0.0, 0.0, false);
@@ -205,8 +221,8 @@ fn main() {
                 for dy in (-samples_per_pixel / 2)..div_up(samples_per_pixel, 2) {
                     let u = (f64::from(x) + f64::from(dx) / f64::from(samples_per_pixel)) / f64::from(image_width - 1);
                     let v = (f64::from(y) + f64::from(dy) / f64::from(samples_per_pixel)) / f64::from(image_height - 1);
-                    let ray = camera.get_ray(u, v);
-                    pixel_color = pixel_color + trace_ray(&world, &ray, &light, max_bounces);
+                    let ray = camera.get_ray_differential(u, v, du, dv);
+                    pixel_color = pixel_color + trace_ray(&scene, &ray, max_bounces);
                 }
             }
 
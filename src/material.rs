@@ -1,16 +1,320 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
 use crate::vec3::Vec3;
+use crate::measured::MeasuredBrdf;
+use crate::subsurface::Subsurface;
+use crate::thinfilm::ThinFilm;
+use crate::texture::Texture;
+use crate::hit::HitRecord;
+use crate::shader::Node;
+use crate::brdf::fresnel_conductor;
+use crate::multiscatter::average_fresnel;
 
 use Vec3 as Color;
 
-#[derive(Debug, Clone, Copy)]
+/// Complex index of refraction `eta + i k` of a metal, per RGB channel.
+/// `average_fresnel` is the hemispherical average of its reflectance, kept
+/// for the multiple-scattering lobe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+    pub average_fresnel: Color
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color) -> Conductor {
+        let average_fresnel = average_fresnel(|cos_theta| fresnel_conductor(cos_theta, eta, k));
+
+        Conductor { eta, k, average_fresnel }
+    }
+
+    /// Reflectance at normal incidence.
+    pub fn f0(&self) -> Color {
+        let one = Color::new(1.0, 1.0, 1.0, false);
+        let k2 = self.k * self.k;
+
+        ((self.eta - one) * (self.eta - one) + k2) / ((self.eta + one) * (self.eta + one) + k2)
+    }
+}
+
+/// Material parameters that may be driven by a texture. Scalar parameters
+/// read the first channel of the texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter {
+    Albedo,
+    Roughness,
+    Metallic,
+    Anisotropy,
+    AnisotropyRotation,
+    SpecularTint,
+    Sheen,
+    SheenTint,
+    Clearcoat,
+    ClearcoatRoughness,
+    Subsurface,
+    Transmission,
+    FilmThickness
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 13] = [
+        Parameter::Albedo,
+        Parameter::Roughness,
+        Parameter::Metallic,
+        Parameter::Anisotropy,
+        Parameter::AnisotropyRotation,
+        Parameter::SpecularTint,
+        Parameter::Sheen,
+        Parameter::SheenTint,
+        Parameter::Clearcoat,
+        Parameter::ClearcoatRoughness,
+        Parameter::Subsurface,
+        Parameter::Transmission,
+        Parameter::FilmThickness
+    ];
+}
+
+/// Principled surface description. `albedo` is the base color, `ior` drives
+/// both the dielectric specular reflectance and refraction, `conductor`
+/// optionally gives metals a measured Fresnel response, and the remaining
+/// lobes (sheen, clearcoat, subsurface) are off when zero. A `measured` BRDF
+/// replaces all reflection lobes, `scattering` turns the surface into the
+/// boundary of a volume rendered with random walks, and `thin_film` coats
+/// the specular reflection with an interference layer. `textures` override
+/// parameters per hit, while `normal_map` and `bump_map` (scaled by
+/// `bump_scale`) perturb the shading normal.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub albedo: Color,
     pub roughness: f64,
-    pub metallic: f64
+    pub metallic: f64,
+    pub anisotropy: f64,
+    pub anisotropy_rotation: f64,
+    pub specular_tint: f64,
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    pub subsurface: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    pub conductor: Option<Conductor>,
+    pub measured: Option<Arc<MeasuredBrdf>>,
+    pub scattering: Option<Subsurface>,
+    pub thin_film: Option<ThinFilm>,
+    pub textures: Vec<(Parameter, Texture)>,
+    pub normal_map: Option<Texture>,
+    pub bump_map: Option<Texture>,
+    pub bump_scale: f64,
+    pub absorption: Color,
+    pub thin_walled: bool,
+    pub priority: u32
 }
 
 impl Material {
     pub fn new(albedo: Color, roughness: f64, metallic: f64) -> Material {
-        Material { albedo, roughness, metallic }
+        Material {
+            albedo,
+            roughness,
+            metallic,
+            anisotropy: 0.0,
+            anisotropy_rotation: 0.0,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            subsurface: 0.0,
+            transmission: 0.0,
+            ior: 1.5,
+            conductor: None,
+            measured: None,
+            scattering: None,
+            thin_film: None,
+            textures: Vec::new(),
+            normal_map: None,
+            bump_map: None,
+            bump_scale: 1.0,
+            absorption: Color::new(0.0, 0.0, 0.0, false),
+            thin_walled: false,
+            priority: 0
+        }
+    }
+
+    /// Fully transmissive material such as glass or water. `absorption` is the
+    /// Beer–Lambert attenuation coefficient per unit of distance travelled
+    /// inside the medium. Where several transmissive objects overlap, the
+    /// one with the highest `priority` defines the medium.
+    pub fn dielectric(ior: f64, roughness: f64, absorption: Color) -> Material {
+        Material {
+            transmission: 1.0,
+            ior,
+            absorption,
+            ..Material::new(Color::new(1.0, 1.0, 1.0, false), roughness, 0.0)
+        }
+    }
+
+    /// Metal whose reflectance follows the exact Fresnel equations for the
+    /// given complex index of refraction.
+    pub fn conductor(conductor: Conductor, roughness: f64) -> Material {
+        Material {
+            conductor: Some(conductor),
+            ..Material::new(conductor.f0(), roughness, 1.0)
+        }
+    }
+
+    /// Material reflecting according to tabulated measurements. It is
+    /// importance sampled through a GGX lobe fitted to the measured highlight.
+    pub fn measured(brdf: Arc<MeasuredBrdf>) -> Material {
+        Material {
+            roughness: brdf.fitted_alpha(),
+            measured: Some(brdf),
+            ..Material::new(Color::new(0.5, 0.5, 0.5, false), 1.0, 0.0)
+        }
+    }
+
+    /// Translucent material such as skin, wax or milk, whose light scatters
+    /// inside the closed object it is applied to. `mean_free_path` is given
+    /// per channel in scene units.
+    pub fn scattering(albedo: Color, mean_free_path: Color, ior: f64) -> Material {
+        Material {
+            ior,
+            scattering: Some(Subsurface::new(albedo, mean_free_path)),
+            ..Material::new(albedo, 0.0, 0.0)
+        }
+    }
+
+    /// Anisotropic metal such as brushed aluminium. `anisotropy` in `[0, 1]`
+    /// stretches the highlight along the surface tangent, which is rotated
+    /// around the normal by `rotation` degrees.
+    pub fn anisotropic(albedo: Color, roughness: f64, metallic: f64, anisotropy: f64, rotation: f64) -> Material {
+        Material {
+            anisotropy,
+            anisotropy_rotation: rotation,
+            ..Material::new(albedo, roughness, metallic)
+        }
+    }
+
+    /// Value of a texturable parameter, scalars being spread over all
+    /// channels.
+    pub fn parameter(&self, parameter: Parameter) -> Color {
+        let scalar = match parameter {
+            Parameter::Albedo => return self.albedo,
+            Parameter::Roughness => self.roughness,
+            Parameter::Metallic => self.metallic,
+            Parameter::Anisotropy => self.anisotropy,
+            Parameter::AnisotropyRotation => self.anisotropy_rotation,
+            Parameter::SpecularTint => self.specular_tint,
+            Parameter::Sheen => self.sheen,
+            Parameter::SheenTint => self.sheen_tint,
+            Parameter::Clearcoat => self.clearcoat,
+            Parameter::ClearcoatRoughness => self.clearcoat_roughness,
+            Parameter::Subsurface => self.subsurface,
+            Parameter::Transmission => self.transmission,
+            Parameter::FilmThickness => self.thin_film.map_or(0.0, |film| film.thickness)
+        };
+
+        Color::new(scalar, scalar, scalar, false)
+    }
+
+    pub fn set_parameter(&mut self, parameter: Parameter, value: Color) {
+        match parameter {
+            Parameter::Albedo => self.albedo = value,
+            Parameter::Roughness => self.roughness = value.x(),
+            Parameter::Metallic => self.metallic = value.x(),
+            Parameter::Anisotropy => self.anisotropy = value.x(),
+            Parameter::AnisotropyRotation => self.anisotropy_rotation = value.x(),
+            Parameter::SpecularTint => self.specular_tint = value.x(),
+            Parameter::Sheen => self.sheen = value.x(),
+            Parameter::SheenTint => self.sheen_tint = value.x(),
+            Parameter::Clearcoat => self.clearcoat = value.x(),
+            Parameter::ClearcoatRoughness => self.clearcoat_roughness = value.x(),
+            Parameter::Subsurface => self.subsurface = value.x(),
+            Parameter::Transmission => self.transmission = value.x(),
+            Parameter::FilmThickness => {
+                if let Some(film) = &mut self.thin_film {
+                    film.thickness = value.x();
+                }
+            }
+        }
+    }
+
+    /// Material with its textured parameters evaluated at the hit, `view`
+    /// pointing towards the viewer. Media are tracked with the unevaluated
+    /// material, so the index of refraction and the absorption cannot be
+    /// textured.
+    pub fn evaluate(&self, hit_record: &HitRecord, view: Vec3) -> Cow<'_, Material> {
+        if self.textures.is_empty() {
+            return Cow::Borrowed(self);
+        }
+
+        let mut material = self.clone();
+
+        for (parameter, texture) in &self.textures {
+            material.set_parameter(*parameter, texture.value(hit_record, view));
+        }
+
+        Cow::Owned(material)
+    }
+
+    /// Blend of two materials by `mask`, 0 selecting `a` and 1 `b`. Every
+    /// texturable parameter is mixed, everything else (Fresnel model, index
+    /// of refraction, media) comes from `a`.
+    pub fn blend(a: &Material, b: &Material, mask: Node) -> Material {
+        let source = |material: &Material, parameter: Parameter| {
+            match material.textures.iter().rev().find(|(textured, _)| *textured == parameter) {
+                Some((_, texture)) => Node::Texture(texture.clone()),
+                None => Node::Constant(material.parameter(parameter))
+            }
+        };
+
+        let textures = Parameter::ALL.iter()
+            .filter(|parameter| **parameter != Parameter::FilmThickness || a.thin_film.is_some())
+            .filter_map(|&parameter| {
+                let (from, to) = (source(a, parameter), source(b, parameter));
+
+                if from == to {
+                    return None;
+                }
+
+                Some((parameter, Texture::Graph(Box::new(Node::mix(from, to, mask.clone())))))
+            })
+            .collect();
+
+        Material { textures, ..a.clone() }
+    }
+
+    /// Weight of the specular transmission lobe; metals never transmit.
+    pub fn transmission_weight(&self) -> f64 {
+        self.transmission * (1.0 - self.metallic)
+    }
+
+    /// GGX roughness along the tangent and the bitangent.
+    pub fn alpha(&self) -> (f64, f64) {
+        let aspect = (1.0 - 0.9 * self.anisotropy.clamp(0.0, 1.0)).sqrt();
+        let alpha_x = (self.roughness / aspect).max(0.001);
+        let alpha_z = (self.roughness * aspect).max(0.001);
+
+        (alpha_x, alpha_z)
+    }
+
+    /// Dielectric treated as an infinitely thin slab, e.g. a window pane.
+    /// Transmitted rays keep their direction and no absorption is applied.
+    pub fn thin_dielectric(ior: f64, roughness: f64) -> Material {
+        Material {
+            thin_walled: true,
+            ..Material::dielectric(ior, roughness, Color::new(0.0, 0.0, 0.0, false))
+        }
+    }
+
+    /// Soap bubble: a free-standing film of water `thickness` nanometers
+    /// thick. The film is the whole wall, so light passes through unbent.
+    pub fn soap_bubble(thickness: f64) -> Material {
+        Material {
+            thin_film: Some(ThinFilm::new(thickness, 1.33)),
+            ..Material::thin_dielectric(1.0, 0.0)
+        }
     }
 }
@@ -1,3 +1,8 @@
+/// Bound on the relative rounding error of three floating point operations.
+pub const GAMMA_3: f64 = 3.0 * f64::EPSILON / (1.0 - 3.0 * f64::EPSILON);
+pub const GAMMA_5: f64 = 5.0 * f64::EPSILON / (1.0 - 5.0 * f64::EPSILON);
+pub const GAMMA_7: f64 = 7.0 * f64::EPSILON / (1.0 - 7.0 * f64::EPSILON);
+
 pub fn div_up(a: i32, b: i32) -> i32 {
     (a + (b - 1)) / b
 }
@@ -0,0 +1,231 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+
+use Vec3 as Color;
+
+const THETA_HALF_RES: usize = 90;
+const THETA_DIFF_RES: usize = 90;
+const PHI_DIFF_RES: usize = 180;
+const SAMPLES_COUNT: usize = THETA_HALF_RES * THETA_DIFF_RES * PHI_DIFF_RES;
+
+const RED_SCALE: f64 = 1.0 / 1500.0;
+const GREEN_SCALE: f64 = 1.15 / 1500.0;
+const BLUE_SCALE: f64 = 1.66 / 1500.0;
+
+/// Isotropic BRDF tabulated in the MERL half-angle/difference-angle
+/// parameterization. Directions are expressed in the local shading frame
+/// used by `brdf` (`y` along the normal).
+pub struct MeasuredBrdf {
+    data: Vec<f64>,
+    fitted_alpha: f64
+}
+
+impl MeasuredBrdf {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<MeasuredBrdf> {
+        MeasuredBrdf::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parses a MERL `.binary` file: three little-endian `i32` dimensions
+    /// followed by the red, green and blue planes as `f64`.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<MeasuredBrdf> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < 12 {
+            return Err(invalid("MERL file is missing its header"));
+        }
+
+        let dims: Vec<usize> = bytes[..12]
+            .chunks_exact(4)
+            .map(|chunk| i32::from_le_bytes(chunk.try_into().unwrap()).max(0) as usize)
+            .collect();
+
+        let count = dims[0].checked_mul(dims[1]).and_then(|count| count.checked_mul(dims[2]));
+        if count != Some(SAMPLES_COUNT) {
+            return Err(invalid("MERL file has unexpected dimensions"));
+        }
+
+        if bytes.len() != 12 + 3 * SAMPLES_COUNT * 8 {
+            return Err(invalid("MERL file has unexpected size"));
+        }
+
+        let data: Vec<f64> = bytes[12..]
+            .chunks_exact(8)
+            .enumerate()
+            .map(|(idx, chunk)| {
+                let scale = match idx / SAMPLES_COUNT {
+                    0 => RED_SCALE,
+                    1 => GREEN_SCALE,
+                    _ => BLUE_SCALE
+                };
+
+                // negative samples mark missing measurements
+                (f64::from_le_bytes(chunk.try_into().unwrap()) * scale).max(0.0)
+            })
+            .collect();
+
+        Ok(MeasuredBrdf::from_data(data))
+    }
+
+    /// Builds a BRDF from already scaled red, green and blue planes.
+    pub fn from_data(data: Vec<f64>) -> MeasuredBrdf {
+        assert_eq!(data.len(), 3 * SAMPLES_COUNT, "Measured BRDF data has unexpected size");
+
+        let mut brdf = MeasuredBrdf { data, fitted_alpha: 1.0 };
+        brdf.fitted_alpha = brdf.fit_ggx_alpha();
+        brdf
+    }
+
+    /// Roughness of the GGX lobe that best matches the measured highlight,
+    /// used to importance sample the measurement.
+    pub fn fitted_alpha(&self) -> f64 {
+        self.fitted_alpha
+    }
+
+    pub fn eval(&self, v: Vec3, l: Vec3) -> Color {
+        if v.y() <= 0.0 || l.y() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0, false);
+        }
+
+        let (theta_half, theta_diff, phi_diff) = half_diff_angles(v, l);
+
+        let th = theta_half_coordinate(theta_half);
+        let td = (theta_diff / std::f64::consts::FRAC_PI_2 * THETA_DIFF_RES as f64).min((THETA_DIFF_RES - 1) as f64);
+        let pd = phi_diff / std::f64::consts::PI * PHI_DIFF_RES as f64;
+
+        Color::new(
+            self.interpolate(0, th, td, pd),
+            self.interpolate(1, th, td, pd),
+            self.interpolate(2, th, td, pd),
+            false
+        )
+    }
+
+    fn sample(&self, channel: usize, th: usize, td: usize, pd: usize) -> f64 {
+        self.data[channel * SAMPLES_COUNT + (th * THETA_DIFF_RES + td) * PHI_DIFF_RES + pd]
+    }
+
+    /// Trilinear interpolation between table entries. `phi_diff` wraps
+    /// around since the table covers half a period of the reciprocal BRDF.
+    fn interpolate(&self, channel: usize, th: f64, td: f64, pd: f64) -> f64 {
+        let th0 = th.floor() as usize;
+        let td0 = td.floor() as usize;
+        let pd0 = pd.floor() as usize % PHI_DIFF_RES;
+        let th1 = (th0 + 1).min(THETA_HALF_RES - 1);
+        let td1 = (td0 + 1).min(THETA_DIFF_RES - 1);
+        let pd1 = (pd0 + 1) % PHI_DIFF_RES;
+        let (fth, ftd, fpd) = (th - th.floor(), td - td.floor(), pd - pd.floor());
+
+        let mut value = 0.0;
+
+        for (i, wi) in [(th0, 1.0 - fth), (th1, fth)] {
+            for (j, wj) in [(td0, 1.0 - ftd), (td1, ftd)] {
+                for (k, wk) in [(pd0, 1.0 - fpd), (pd1, fpd)] {
+                    value += wi * wj * wk * self.sample(channel, i, j, k);
+                }
+            }
+        }
+
+        value
+    }
+
+    fn fit_ggx_alpha(&self) -> f64 {
+        // luminance of the measured profile along theta_half at theta_diff = 0
+        let profile: Vec<(f64, f64)> = (0..THETA_HALF_RES)
+            .map(|th| {
+                let theta_half = (th as f64 / THETA_HALF_RES as f64).powf(2.0) * std::f64::consts::FRAC_PI_2;
+                let value = (0..3).map(|channel| self.sample(channel, th, 0, 0)).sum::<f64>() / 3.0;
+                (theta_half, value)
+            })
+            .filter(|(_, value)| *value > 0.0)
+            .collect();
+
+        if profile.len() < 2 {
+            return 1.0;
+        }
+
+        let mut best = (f64::INFINITY, 1.0);
+
+        for step in 0..64 {
+            let alpha = 0.005 * (1.0_f64 / 0.005).powf(step as f64 / 63.0);
+            let ggx = |theta_half: f64| {
+                let cos2 = theta_half.cos().powf(2.0);
+                let tan2 = (1.0 - cos2) / cos2;
+                1.0 / (cos2 * cos2 * (alpha * alpha + tan2).powf(2.0))
+            };
+
+            // compare shapes in log space, normalized at the peak
+            let offset = profile[0].1.ln() - ggx(profile[0].0).ln();
+            let error: f64 = profile.iter()
+                .map(|(theta_half, value)| (value.ln() - ggx(*theta_half).ln() - offset).powf(2.0))
+                .sum();
+
+            if error < best.0 {
+                best = (error, alpha);
+            }
+        }
+
+        best.1
+    }
+}
+
+impl PartialEq for MeasuredBrdf {
+    /// Measurements are compared by identity, the data is never duplicated.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for MeasuredBrdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MeasuredBrdf")
+            .field("fitted_alpha", &self.fitted_alpha)
+            .finish()
+    }
+}
+
+/// The theta_half axis is sampled more densely near the highlight.
+fn theta_half_coordinate(theta_half: f64) -> f64 {
+    if theta_half <= 0.0 {
+        return 0.0;
+    }
+
+    let linear = theta_half / std::f64::consts::FRAC_PI_2 * THETA_HALF_RES as f64;
+
+    (linear * THETA_HALF_RES as f64).sqrt().min((THETA_HALF_RES - 1) as f64)
+}
+
+fn rotate(v: Vec3, axis: Vec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+
+    v * cos + axis * (axis.dot(v) * (1.0 - cos)) + axis.cross(v) * sin
+}
+
+/// Converts a pair of directions into the Rusinkiewicz half/difference
+/// angles `(theta_half, theta_diff, phi_diff)`, with `phi_diff` folded into
+/// `[0, pi)`.
+pub fn half_diff_angles(v: Vec3, l: Vec3) -> (f64, f64, f64) {
+    // swap into a z-up frame
+    let v = Vec3::new(v.x(), v.z(), v.y(), false).normalized();
+    let l = Vec3::new(l.x(), l.z(), l.y(), false).normalized();
+    let half = (v + l).normalized();
+
+    let theta_half = half.z().clamp(-1.0, 1.0).acos();
+    let phi_half = half.y().atan2(half.x());
+
+    let normal = Vec3::new(0.0, 0.0, 1.0, false);
+    let bitangent = Vec3::new(0.0, 1.0, 0.0, false);
+    let diff = rotate(rotate(l, normal, -phi_half), bitangent, -theta_half);
+
+    let theta_diff = diff.z().clamp(-1.0, 1.0).acos();
+    let mut phi_diff = diff.y().atan2(diff.x());
+
+    if phi_diff < 0.0 {
+        phi_diff += std::f64::consts::PI;
+    }
+
+    (theta_half, theta_diff, phi_diff.min(std::f64::consts::PI))
+}
@@ -0,0 +1,69 @@
+use crate::material::Material;
+
+/// Transmissive materials a path is currently inside of. The medium that
+/// governs refraction and absorption is the one with the highest priority,
+/// with the most recently entered one winning ties.
+#[derive(Debug, Clone, Default)]
+pub struct MediumStack {
+    media: Vec<Material>
+}
+
+/// Indices of refraction on both sides of an interface, ordered along the
+/// direction of travel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interface {
+    pub eta_i: f64,
+    pub eta_t: f64
+}
+
+impl Interface {
+    /// Ratio of the incident to the transmitted index of refraction.
+    pub fn eta(&self) -> f64 {
+        self.eta_i / self.eta_t
+    }
+}
+
+impl MediumStack {
+    pub fn new() -> MediumStack {
+        MediumStack { media: Vec::new() }
+    }
+
+    pub fn current(&self) -> Option<&Material> {
+        self.media.iter().max_by_key(|material| material.priority)
+    }
+
+    pub fn ior(&self) -> f64 {
+        self.current().map_or(1.0, |material| material.ior)
+    }
+
+    /// Returns the stack after the path crosses the boundary of `material`.
+    pub fn crossed(&self, material: &Material, entering: bool) -> MediumStack {
+        let mut media = self.media.clone();
+
+        if entering {
+            media.push(material.clone());
+        } else if let Some(idx) = media.iter().rposition(|medium| medium == material) {
+            media.remove(idx);
+        }
+
+        MediumStack { media }
+    }
+
+    /// A boundary is false when the material is overlapped by a medium of
+    /// higher priority, in which case the path should pass straight through.
+    pub fn is_false_boundary(&self, material: &Material, entering: bool) -> bool {
+        match self.current() {
+            Some(current) if entering => material.priority < current.priority,
+            Some(current) => current != material && self.media.contains(material),
+            None => false
+        }
+    }
+
+    pub fn interface(&self, material: &Material, entering: bool) -> Interface {
+        if entering {
+            Interface { eta_i: self.ior(), eta_t: material.ior }
+        } else {
+            Interface { eta_i: material.ior, eta_t: self.crossed(material, false).ior() }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::vec3::Vec3;
+use crate::ray::Ray;
+use crate::hit::{HitRecord, Hit, Visibility};
+use crate::material::Material;
+use crate::transform::TransformMatrix;
+use crate::aabb::Aabb;
+use crate::math::GAMMA_7;
+
+use Vec3 as Point3;
+
+/// Maximum number of triangles in a BVH leaf.
+const LEAF_SIZE: usize = 4;
+
+fn direction(v: Vec3) -> Vec3 {
+    Vec3::new(v.x(), v.y(), v.z(), false)
+}
+
+/// Node of a bounding volume hierarchy over the triangles of a mesh. Leaves
+/// cover `count` triangles starting at `start`, inner nodes have their
+/// first child right after them and the second at `start`.
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+    start: usize,
+    count: usize
+}
+
+/// Indexed triangle mesh with per-vertex normals and uv coordinates, in
+/// counter-clockwise winding. Triangles are kept in a BVH, so `triangles`
+/// is reordered on construction.
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub triangles: Vec<[usize; 3]>,
+    pub material: Material,
+    pub transform_matrix: Option<TransformMatrix>,
+    pub name: String,
+    pub visibility: Visibility,
+    bvh: Vec<BvhNode>
+}
+
+/// Area weighted vertex normals. Vertices only used by degenerate
+/// triangles get a zero normal.
+pub fn vertex_normals(positions: &[Point3], triangles: &[[usize; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::new(0.0, 0.0, 0.0, false); positions.len()];
+
+    for &[a, b, c] in triangles {
+        // the cross product is twice the area, which weights the sum
+        let face = direction(positions[b] - positions[a]).cross(direction(positions[c] - positions[a]));
+
+        for i in [a, b, c] {
+            normals[i] = normals[i] + face;
+        }
+    }
+
+    normals.into_iter()
+        .map(|normal| if normal.length_squared() > 0.0 { normal.normalized() } else { normal })
+        .collect()
+}
+
+impl Mesh {
+    /// Builds a mesh from its vertex attributes. `normals` and `uvs` may be
+    /// empty, in which case normals are computed from the faces and uvs are
+    /// zero; zero normals are also replaced by computed ones.
+    pub fn new(positions: Vec<Point3>, normals: Vec<Vec3>, uvs: Vec<(f64, f64)>, triangles: Vec<[usize; 3]>, material: Material, transform_matrix: Option<TransformMatrix>) -> Mesh {
+        assert!(triangles.iter().flatten().all(|&i| i < positions.len()), "Mesh has out of range vertex index");
+
+        let computed = vertex_normals(&positions, &triangles);
+        let normals = if normals.len() == positions.len() {
+            normals.into_iter()
+                .zip(computed)
+                .map(|(normal, computed)| if normal.length_squared() > 0.0 { direction(normal).normalized() } else { computed })
+                .collect()
+        } else {
+            computed
+        };
+        let uvs = if uvs.len() == positions.len() { uvs } else { vec![(0.0, 0.0); positions.len()] };
+
+        let mut mesh = Mesh { positions, normals, uvs, triangles, material, transform_matrix, name: String::new(), visibility: Visibility::default(), bvh: Vec::new() };
+        mesh.build_bvh();
+        mesh
+    }
+
+    /// Loads a Wavefront OBJ file. Only vertices, texture coordinates,
+    /// normals and faces are read, polygons are split into fans.
+    pub fn load_obj<P: AsRef<Path>>(path: P, material: Material, transform_matrix: Option<TransformMatrix>) -> io::Result<Mesh> {
+        Mesh::from_obj(&fs::read_to_string(path)?, material, transform_matrix)
+    }
+
+    pub fn from_obj(source: &str, material: Material, transform_matrix: Option<TransformMatrix>) -> io::Result<Mesh> {
+        let invalid = |line: usize, message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line + 1, message));
+
+        let mut obj_positions = Vec::new();
+        let mut obj_uvs = Vec::new();
+        let mut obj_normals = Vec::new();
+        // vertices are unique per combination of position, uv and normal
+        let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+        let (mut positions, mut normals, mut uvs, mut triangles) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+
+        for (number, line) in source.lines().enumerate() {
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next();
+            let values = |tokens: std::str::SplitWhitespace, count: usize| -> io::Result<Vec<f64>> {
+                let values = tokens.take(count).map(str::parse).collect::<Result<Vec<f64>, _>>()
+                    .map_err(|_| invalid(number, "invalid number"))?;
+
+                if values.len() < count { Err(invalid(number, "missing coordinates")) } else { Ok(values) }
+            };
+
+            match keyword {
+                Some("v") => {
+                    let v = values(tokens, 3)?;
+                    obj_positions.push(Point3::new(v[0], v[1], v[2], true));
+                }
+                Some("vt") => {
+                    let v = values(tokens, 2)?;
+                    obj_uvs.push((v[0], v[1]));
+                }
+                Some("vn") => {
+                    let v = values(tokens, 3)?;
+                    obj_normals.push(Vec3::new(v[0], v[1], v[2], false));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+
+                    for token in tokens {
+                        // indices are 1-based, negative ones count from the end
+                        let resolve = |index: Option<&str>, count: usize| -> io::Result<Option<usize>> {
+                            match index.filter(|index| !index.is_empty()) {
+                                None => Ok(None),
+                                Some(index) => {
+                                    let index: i64 = index.parse().map_err(|_| invalid(number, "invalid index"))?;
+                                    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+
+                                    if resolved < 0 || resolved >= count as i64 {
+                                        Err(invalid(number, "index out of range"))
+                                    } else {
+                                        Ok(Some(resolved as usize))
+                                    }
+                                }
+                            }
+                        };
+
+                        let mut indices = token.split('/');
+                        let position = resolve(indices.next(), obj_positions.len())?.ok_or_else(|| invalid(number, "missing vertex index"))?;
+                        let uv = resolve(indices.next(), obj_uvs.len())?;
+                        let normal = resolve(indices.next(), obj_normals.len())?;
+
+                        let vertex = *vertices.entry((position, uv, normal)).or_insert_with(|| {
+                            positions.push(obj_positions[position]);
+                            uvs.push(uv.map_or((0.0, 0.0), |uv| obj_uvs[uv]));
+                            normals.push(normal.map_or(Vec3::new(0.0, 0.0, 0.0, false), |normal| obj_normals[normal]));
+                            positions.len() - 1
+                        });
+
+                        face.push(vertex);
+                    }
+
+                    if face.len() < 3 {
+                        return Err(invalid(number, "face has fewer than 3 vertices"));
+                    }
+
+                    for i in 1..face.len() - 1 {
+                        triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Mesh::new(positions, normals, uvs, triangles, material, transform_matrix))
+    }
+
+    /// Bounds of the mesh in object space.
+    pub fn bounds(&self) -> Aabb {
+        self.bvh.first().map_or(Aabb::empty(), |node| node.bounds)
+    }
+
+    fn triangle_bounds(&self, triangle: &[usize; 3]) -> Aabb {
+        triangle.iter().fold(Aabb::empty(), |bounds, &i| bounds.grow(self.positions[i]))
+    }
+
+    fn build_bvh(&mut self) {
+        self.bvh.clear();
+
+        if !self.triangles.is_empty() {
+            self.build_node(0, self.triangles.len());
+        }
+    }
+
+    /// Appends the node covering `triangles[start..start + count]`, splitting
+    /// at the median centroid along the widest axis of the centroids.
+    fn build_node(&mut self, start: usize, count: usize) {
+        let range = start..start + count;
+        let bounds = self.triangles[range.clone()].iter().fold(Aabb::empty(), |bounds, triangle| bounds.union(&self.triangle_bounds(triangle)));
+        let index = self.bvh.len();
+        self.bvh.push(BvhNode { bounds, start, count });
+
+        if count <= LEAF_SIZE {
+            return;
+        }
+
+        let centroids = self.triangles[range.clone()].iter().fold(Aabb::empty(), |centroids, triangle| centroids.grow(self.triangle_bounds(triangle).center()));
+        let axis = centroids.longest_axis();
+        let half = count / 2;
+        let positions = &self.positions;
+        let centroid = |triangle: &[usize; 3]| triangle.iter().map(|&i| positions[i][axis]).sum::<f64>();
+
+        self.triangles[range].select_nth_unstable_by(half, |a, b| centroid(a).total_cmp(&centroid(b)));
+
+        self.build_node(start, half);
+        let second = self.bvh.len();
+        self.build_node(start + half, count - half);
+        self.bvh[index] = BvhNode { bounds, start: second, count: 0 };
+    }
+
+    /// Watertight intersection (Woop et al. 2013), returning the distance
+    /// and the barycentric coordinates of the second and third vertices.
+    /// The triangle is sheared into the ray's frame so that rays through a
+    /// shared edge or vertex always hit one of its triangles.
+    fn intersect_triangle(&self, ray: &Ray, triangle: &[usize; 3], t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        let d = ray.direction();
+        let kz = if d.x().abs() > d.y().abs() && d.x().abs() > d.z().abs() {
+            0
+        } else if d.y().abs() > d.z().abs() {
+            1
+        } else {
+            2
+        };
+        let (kx, ky) = ((kz + 1) % 3, (kz + 2) % 3);
+        let (sx, sy, sz) = (-d[kx] / d[kz], -d[ky] / d[kz], 1.0 / d[kz]);
+
+        // vertices relative to the origin, in the sheared frame where the ray
+        // points along z
+        let vertex = |i: usize| {
+            let p = self.positions[triangle[i]] - ray.origin();
+            (p[kx] + sx * p[kz], p[ky] + sy * p[kz], p[kz] * sz)
+        };
+        let (p0, p1, p2) = (vertex(0), vertex(1), vertex(2));
+
+        let e0 = p1.0 * p2.1 - p1.1 * p2.0;
+        let e1 = p2.0 * p0.1 - p2.1 * p0.0;
+        let e2 = p0.0 * p1.1 - p0.1 * p1.0;
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None;
+        }
+
+        let determinant = e0 + e1 + e2;
+
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let t = (e0 * p0.2 + e1 * p1.2 + e2 * p2.2) / determinant;
+
+        if t < t_min || t > t_max { None } else { Some((t, e1 / determinant, e2 / determinant)) }
+    }
+
+    fn record(&self, ray: &Ray, triangle: &[usize; 3], t: f64, b1: f64, b2: f64) -> HitRecord<'_> {
+        let [i0, i1, i2] = *triangle;
+        let b0 = 1.0 - b1 - b2;
+        // interpolating the vertices is more accurate than following the ray
+        let point = self.positions[i0] * b0 + self.positions[i1] * b1 + self.positions[i2] * b2;
+        let point = Point3::new(point.x(), point.y(), point.z(), true);
+        let weighted = |i: usize, b: f64| {
+            let p = self.positions[i] * b;
+            Vec3::new(p.x().abs(), p.y().abs(), p.z().abs(), false)
+        };
+        let point_error = (weighted(i0, b0) + weighted(i1, b1) + weighted(i2, b2)) * GAMMA_7;
+
+        let edge1 = direction(self.positions[i1] - self.positions[i0]);
+        let edge2 = direction(self.positions[i2] - self.positions[i0]);
+        let geometric_normal = edge1.cross(edge2).normalized();
+        let front_face = ray.direction().dot(geometric_normal) < 0.0;
+        let facing = if front_face { geometric_normal } else { -geometric_normal };
+
+        // interpolated normals are flipped onto the side of the face
+        let mut normal = self.normals[i0] * b0 + self.normals[i1] * b1 + self.normals[i2] * b2;
+        normal = if normal.length_squared() > 1e-24 { normal.normalized() } else { geometric_normal };
+        if normal.dot(geometric_normal) < 0.0 {
+            normal = -normal;
+        }
+
+        let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+        let uv = (
+            uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2,
+            uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2
+        );
+
+        let (du1, dv1) = (uv1.0 - uv0.0, uv1.1 - uv0.1);
+        let (du2, dv2) = (uv2.0 - uv0.0, uv2.1 - uv0.1);
+        let uv_determinant = du1 * dv2 - dv1 * du2;
+        let (dpdu, dpdv) = if uv_determinant.abs() > 1e-12 {
+            (
+                (edge1 * dv2 - edge2 * dv1) / uv_determinant,
+                (edge2 * du1 - edge1 * du2) / uv_determinant
+            )
+        } else {
+            geometric_normal.orthonormal_basis()
+        };
+
+        HitRecord {
+            t_min: t,
+            point,
+            point_error,
+            object_point: point,
+            normal: if front_face { normal } else { -normal },
+            geometric_normal: facing,
+            uv,
+            dpdu,
+            dpdv,
+            dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+            dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
+            front_face,
+            material: &self.material,
+            name: &self.name
+        }
+    }
+}
+
+impl Hit for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(usize, f64, f64, f64)> = None;
+        let mut t_max = t_max;
+        let mut stack = vec![0];
+
+        if self.bvh.is_empty() {
+            return None;
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.bvh[index];
+
+            if !node.bounds.hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+
+            for i in node.start..node.start + node.count {
+                if let Some((t, b1, b2)) = self.intersect_triangle(ray, &self.triangles[i], t_min, t_max) {
+                    t_max = t;
+                    closest = Some((i, t, b1, b2));
+                }
+            }
+        }
+
+        closest.map(|(i, t, b1, b2)| self.record(ray, &self.triangles[i], t, b1, b2))
+    }
+
+    fn occluded(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut stack = if self.bvh.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(index) = stack.pop() {
+            let node = &self.bvh[index];
+
+            if !node.bounds.hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(index + 1);
+                continue;
+            }
+
+            let triangles = &self.triangles[node.start..node.start + node.count];
+
+            if triangles.iter().any(|triangle| self.intersect_triangle(ray, triangle, t_min, t_max).is_some()) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn transform_matrix(&self) -> Option<&TransformMatrix> {
+        self.transform_matrix.as_ref()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::OnceLock;
+
+use crate::vec3::Vec3;
+use crate::brdf::{ggx_vndf, g1, fresnel_dielectric};
+
+const TABLE_SIZE: usize = 32;
+const IOR_TABLE_SIZE: usize = 16;
+const STRATA_COUNT: usize = 16;
+const MAX_IOR: f64 = 3.0;
+const FRESNEL_SAMPLES_COUNT: usize = 16;
+
+/// Directional albedo of single-scattering GGX, tabulated over the cosine of
+/// the view angle and the roughness, and for rough dielectric interfaces also
+/// over the index of refraction. Values are cell-centered.
+struct AlbedoTables {
+    ggx: Vec<f64>,
+    ggx_average: Vec<f64>,
+    dielectric_entering: Vec<f64>,
+    dielectric_exiting: Vec<f64>
+}
+
+static TABLES: OnceLock<AlbedoTables> = OnceLock::new();
+
+fn tables() -> &'static AlbedoTables {
+    TABLES.get_or_init(AlbedoTables::generate)
+}
+
+fn cell_center(i: usize, size: usize) -> f64 {
+    (i as f64 + 0.5) / size as f64
+}
+
+fn table_ior(i: usize) -> f64 {
+    1.0 + (MAX_IOR - 1.0) * cell_center(i, IOR_TABLE_SIZE)
+}
+
+/// Integrates `weight` over microfacet normals sampled from the visible
+/// normal distribution with stratified, deterministic samples.
+fn directional_albedo<F: Fn(Vec3, Vec3) -> f64>(cos_theta: f64, alpha: f64, weight: F) -> f64 {
+    let v = Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), cos_theta, 0.0, false);
+    let mut sum = 0.0;
+
+    for i in 0..STRATA_COUNT {
+        for j in 0..STRATA_COUNT {
+            let m = ggx_vndf(v, alpha, alpha, cell_center(i, STRATA_COUNT), cell_center(j, STRATA_COUNT));
+            sum += weight(v, m);
+        }
+    }
+
+    sum / (STRATA_COUNT * STRATA_COUNT) as f64
+}
+
+fn reflection_weight(alpha: f64, v: Vec3, m: Vec3) -> f64 {
+    let l = (-v).reflect(m);
+
+    if l.y() > 0.0 { g1(alpha, alpha, l) } else { 0.0 }
+}
+
+fn dielectric_weight(alpha: f64, eta: f64, v: Vec3, m: Vec3) -> f64 {
+    let fresnel = fresnel_dielectric(v.dot(m), eta);
+    let transmission = match (-v).refract(m, eta) {
+        Some(t) if t.y() < 0.0 => (1.0 - fresnel) * g1(alpha, alpha, -t),
+        _ => 0.0
+    };
+
+    fresnel * reflection_weight(alpha, v, m) + transmission
+}
+
+impl AlbedoTables {
+    fn generate() -> AlbedoTables {
+        let mut ggx = Vec::with_capacity(TABLE_SIZE * TABLE_SIZE);
+        let mut ggx_average = Vec::with_capacity(TABLE_SIZE);
+
+        for a in 0..TABLE_SIZE {
+            let alpha = cell_center(a, TABLE_SIZE);
+            let mut average = 0.0;
+
+            for c in 0..TABLE_SIZE {
+                let cos_theta = cell_center(c, TABLE_SIZE);
+                let albedo = directional_albedo(cos_theta, alpha, |v, m| reflection_weight(alpha, v, m));
+                average += 2.0 * albedo * cos_theta / TABLE_SIZE as f64;
+                ggx.push(albedo);
+            }
+
+            ggx_average.push(average);
+        }
+
+        let dielectric = |exiting: bool| {
+            let mut table = Vec::with_capacity(IOR_TABLE_SIZE * TABLE_SIZE * TABLE_SIZE);
+
+            for i in 0..IOR_TABLE_SIZE {
+                let eta = if exiting { table_ior(i) } else { 1.0 / table_ior(i) };
+
+                for a in 0..TABLE_SIZE {
+                    let alpha = cell_center(a, TABLE_SIZE);
+
+                    for c in 0..TABLE_SIZE {
+                        let cos_theta = cell_center(c, TABLE_SIZE);
+                        table.push(directional_albedo(cos_theta, alpha, |v, m| dielectric_weight(alpha, eta, v, m)));
+                    }
+                }
+            }
+
+            table
+        };
+
+        AlbedoTables {
+            ggx,
+            ggx_average,
+            dielectric_entering: dielectric(false),
+            dielectric_exiting: dielectric(true)
+        }
+    }
+}
+
+/// Splits a coordinate in `[0, 1]` into the two neighbouring cells of a
+/// cell-centered table and the interpolation weight between them.
+fn lookup(x: f64, size: usize) -> (usize, usize, f64) {
+    let t = (x.clamp(0.0, 1.0) * size as f64 - 0.5).clamp(0.0, (size - 1) as f64);
+    let i0 = t.floor() as usize;
+    let i1 = (i0 + 1).min(size - 1);
+
+    (i0, i1, t - i0 as f64)
+}
+
+fn bilinear(table: &[f64], cos_theta: f64, alpha: f64) -> f64 {
+    let (a0, a1, ta) = lookup(alpha, TABLE_SIZE);
+    let (c0, c1, tc) = lookup(cos_theta, TABLE_SIZE);
+    let at = |a: usize, c: usize| table[a * TABLE_SIZE + c];
+
+    let e0 = at(a0, c0) * (1.0 - tc) + at(a0, c1) * tc;
+    let e1 = at(a1, c0) * (1.0 - tc) + at(a1, c1) * tc;
+
+    e0 * (1.0 - ta) + e1 * ta
+}
+
+/// Fraction of energy reflected by single-scattering GGX with a perfect
+/// mirror Fresnel term.
+pub fn ggx_albedo(cos_theta: f64, alpha: f64) -> f64 {
+    bilinear(&tables().ggx, cos_theta, alpha)
+}
+
+/// Cosine-weighted hemispherical average of `ggx_albedo`.
+pub fn ggx_average_albedo(alpha: f64) -> f64 {
+    let table = &tables().ggx_average;
+    let (a0, a1, ta) = lookup(alpha, TABLE_SIZE);
+
+    table[a0] * (1.0 - ta) + table[a1] * ta
+}
+
+/// Fraction of energy reflected or transmitted by a rough dielectric
+/// interface, where `eta` is the ratio of the incident to the transmitted
+/// index of refraction.
+pub fn dielectric_albedo(cos_theta: f64, alpha: f64, eta: f64) -> f64 {
+    let (table, ior) = if eta < 1.0 {
+        (&tables().dielectric_entering, 1.0 / eta)
+    } else {
+        (&tables().dielectric_exiting, eta)
+    };
+
+    let (i0, i1, ti) = lookup((ior - 1.0) / (MAX_IOR - 1.0), IOR_TABLE_SIZE);
+    let slice = TABLE_SIZE * TABLE_SIZE;
+    let e0 = bilinear(&table[i0 * slice..(i0 + 1) * slice], cos_theta, alpha);
+    let e1 = bilinear(&table[i1 * slice..(i1 + 1) * slice], cos_theta, alpha);
+
+    e0 * (1.0 - ti) + e1 * ti
+}
+
+/// Cosine-weighted hemispherical average of a Fresnel term.
+pub fn average_fresnel<F: Fn(f64) -> Vec3>(fresnel: F) -> Vec3 {
+    let mut sum = Vec3::new(0.0, 0.0, 0.0, false);
+
+    for i in 0..FRESNEL_SAMPLES_COUNT {
+        let cos_theta = cell_center(i, FRESNEL_SAMPLES_COUNT);
+        sum = sum + fresnel(cos_theta) * (2.0 * cos_theta / FRESNEL_SAMPLES_COUNT as f64);
+    }
+
+    sum
+}
+
+/// Kulla–Conty lobe restoring the energy that single-scattering GGX loses to
+/// light bouncing several times between microfacets.
+pub fn multiple_scattering(f_avg: Vec3, cos_theta_o: f64, cos_theta_i: f64, alpha: f64) -> Vec3 {
+    let e_avg = ggx_average_albedo(alpha);
+
+    if e_avg >= 1.0 {
+        return Vec3::new(0.0, 0.0, 0.0, false);
+    }
+
+    let e_o = ggx_albedo(cos_theta_o, alpha);
+    let e_i = ggx_albedo(cos_theta_i, alpha);
+    let f_ms = (1.0 - e_o) * (1.0 - e_i) / (std::f64::consts::PI * (1.0 - e_avg));
+    let fresnel_ms = f_avg * f_avg * e_avg / (1.0 - f_avg * (1.0 - e_avg));
+
+    fresnel_ms * f_ms
+}
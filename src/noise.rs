@@ -0,0 +1,182 @@
+use crate::vec3::Vec3;
+
+use Vec3 as Point3;
+
+const LACUNARITY: f64 = 2.0;
+const GAIN: f64 = 0.5;
+
+/// Ken Perlin's reference permutation, so that patterns are identical from
+/// one run to the next.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225,
+    140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148,
+    247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32,
+    57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122,
+    60, 211, 133, 230, 220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54,
+    65, 25, 63, 161, 1, 216, 80, 73, 209, 76, 132, 187, 208, 89, 18, 169,
+    200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173, 186, 3, 64,
+    52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212,
+    207, 206, 59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213,
+    119, 248, 152, 2, 44, 154, 163, 70, 221, 153, 101, 155, 167, 43, 172, 9,
+    129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232, 178, 185, 112, 104,
+    218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162, 241,
+    81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157,
+    184, 84, 204, 176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93,
+    222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180
+];
+
+/// Gradient noise function used as the basis of fractal sums.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Basis {
+    Perlin,
+    Simplex
+}
+
+fn hash(i: i64, j: i64, k: i64) -> usize {
+    let p = |x: usize| PERMUTATION[x & 255] as usize;
+
+    p(p(p(i as usize & 255) + (j as usize & 255)) + (k as usize & 255))
+}
+
+/// Dot product of the offset with one of twelve gradient directions
+/// pointing to the edges of a cube.
+fn gradient(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Improved Perlin noise, in `[-1, 1]` and zero at integer lattice points.
+pub fn perlin(p: Point3) -> f64 {
+    let (xf, yf, zf) = (p.x().floor(), p.y().floor(), p.z().floor());
+    let (i, j, k) = (xf as i64, yf as i64, zf as i64);
+    let (x, y, z) = (p.x() - xf, p.y() - yf, p.z() - zf);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |di: i64, dj: i64, dk: i64| {
+        gradient(hash(i + di, j + dj, k + dk), x - di as f64, y - dj as f64, z - dk as f64)
+    };
+
+    lerp(
+        lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u), lerp(corner(0, 1, 0), corner(1, 1, 0), u), v),
+        lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u), lerp(corner(0, 1, 1), corner(1, 1, 1), u), v),
+        w
+    )
+}
+
+/// Simplex noise over a tetrahedral lattice, in about `[-1, 1]`. Cheaper
+/// than Perlin noise and without its axis-aligned artifacts.
+pub fn simplex(p: Point3) -> f64 {
+    const SKEW: f64 = 1.0 / 3.0;
+    const UNSKEW: f64 = 1.0 / 6.0;
+
+    let s = (p.x() + p.y() + p.z()) * SKEW;
+    let (i, j, k) = ((p.x() + s).floor(), (p.y() + s).floor(), (p.z() + s).floor());
+    let t = (i + j + k) * UNSKEW;
+    let x0 = [p.x() - (i - t), p.y() - (j - t), p.z() - (k - t)];
+
+    // the simplex containing the point is found by ranking its coordinates
+    let (o1, o2) = if x0[0] >= x0[1] {
+        if x0[1] >= x0[2] {
+            ([1, 0, 0], [1, 1, 0])
+        } else if x0[0] >= x0[2] {
+            ([1, 0, 0], [1, 0, 1])
+        } else {
+            ([0, 0, 1], [1, 0, 1])
+        }
+    } else if x0[1] < x0[2] {
+        ([0, 0, 1], [0, 1, 1])
+    } else if x0[0] < x0[2] {
+        ([0, 1, 0], [0, 1, 1])
+    } else {
+        ([0, 1, 0], [1, 1, 0])
+    };
+
+    let (i, j, k) = (i as i64, j as i64, k as i64);
+    let mut sum = 0.0;
+
+    for (n, offset) in [[0, 0, 0], o1, o2, [1, 1, 1]].iter().enumerate() {
+        let x = x0[0] - offset[0] as f64 + n as f64 * UNSKEW;
+        let y = x0[1] - offset[1] as f64 + n as f64 * UNSKEW;
+        let z = x0[2] - offset[2] as f64 + n as f64 * UNSKEW;
+        let falloff = 0.6 - x * x - y * y - z * z;
+
+        if falloff > 0.0 {
+            let h = hash(i + offset[0], j + offset[1], k + offset[2]);
+            sum += falloff.powi(4) * gradient(h % 12, x, y, z);
+        }
+    }
+
+    32.0 * sum
+}
+
+fn basis_noise(basis: Basis, p: Point3) -> f64 {
+    match basis {
+        Basis::Perlin => perlin(p),
+        Basis::Simplex => simplex(p)
+    }
+}
+
+/// Sum of `octaves` layers of noise of increasing frequency and decreasing
+/// amplitude, normalized to about `[-1, 1]`.
+pub fn fbm(basis: Basis, p: Point3, octaves: u32) -> f64 {
+    fractal(p, octaves, |p| basis_noise(basis, p))
+}
+
+/// Fractal sum of the absolute value of noise, in about `[0, 1]`.
+pub fn turbulence(basis: Basis, p: Point3, octaves: u32) -> f64 {
+    fractal(p, octaves, |p| basis_noise(basis, p).abs())
+}
+
+fn fractal<F: Fn(Point3) -> f64>(p: Point3, octaves: u32, noise: F) -> f64 {
+    let mut sum = 0.0;
+    let mut norm = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+
+    for _ in 0..octaves.max(1) {
+        sum += amplitude * noise(p * frequency);
+        norm += amplitude;
+        amplitude *= GAIN;
+        frequency *= LACUNARITY;
+    }
+
+    sum / norm
+}
+
+/// Distance to the closest of a set of feature points scattered one per
+/// unit cell (cellular noise).
+pub fn worley(p: Point3) -> f64 {
+    let (i, j, k) = (p.x().floor() as i64, p.y().floor() as i64, p.z().floor() as i64);
+    let mut closest = f64::INFINITY;
+
+    for di in -1..=1 {
+        for dj in -1..=1 {
+            for dk in -1..=1 {
+                let (ci, cj, ck) = (i + di, j + dj, k + dk);
+                let h = hash(ci, cj, ck);
+                let feature = Point3::new(
+                    ci as f64 + PERMUTATION[h] as f64 / 256.0,
+                    cj as f64 + PERMUTATION[(h + 85) & 255] as f64 / 256.0,
+                    ck as f64 + PERMUTATION[(h + 170) & 255] as f64 / 256.0,
+                    true
+                );
+
+                closest = closest.min((feature - p).length());
+            }
+        }
+    }
+
+    closest
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+
+use crate::vec3::Vec3;
+use crate::material::{Material, Conductor};
+
+use Vec3 as Color;
+
+/// Named materials backed by measured optical constants. Conductors use the
+/// complex index of refraction sampled at roughly 650, 550 and 450 nm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Gold,
+    Silver,
+    Copper,
+    Aluminium,
+    Chrome,
+    Iron,
+    Plastic,
+    Rubber
+}
+
+impl Preset {
+    pub fn conductor(&self) -> Option<Conductor> {
+        let (eta, k) = match self {
+            Preset::Gold => ((0.143, 0.374, 1.442), (3.983, 2.385, 1.603)),
+            Preset::Silver => ((0.155, 0.117, 0.138), (4.828, 3.122, 2.147)),
+            Preset::Copper => ((0.200, 0.924, 1.102), (3.912, 2.452, 2.142)),
+            Preset::Aluminium => ((1.657, 0.880, 0.521), (9.224, 6.270, 4.837)),
+            Preset::Chrome => ((3.107, 3.181, 2.323), (3.331, 3.329, 3.135)),
+            Preset::Iron => ((2.911, 2.950, 2.585), (3.089, 2.932, 2.767)),
+            Preset::Plastic | Preset::Rubber => return None
+        };
+
+        Some(Conductor::new(
+            Color::new(eta.0, eta.1, eta.2, false),
+            Color::new(k.0, k.1, k.2, false)
+        ))
+    }
+
+    pub fn material(&self, roughness: f64) -> Material {
+        match self {
+            Preset::Plastic => Material {
+                ior: 1.49,
+                ..Material::new(Color::new(0.8, 0.8, 0.8, false), roughness, 0.0)
+            },
+            Preset::Rubber => Material {
+                ior: 1.52,
+                ..Material::new(Color::new(0.05, 0.05, 0.05, false), roughness.max(0.6), 0.0)
+            },
+            _ => Material::conductor(self.conductor().unwrap(), roughness)
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    /// Parses the lowercase preset names used in scene descriptions.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_lowercase().as_str() {
+            "gold" => Ok(Preset::Gold),
+            "silver" => Ok(Preset::Silver),
+            "copper" => Ok(Preset::Copper),
+            "aluminium" | "aluminum" => Ok(Preset::Aluminium),
+            "chrome" | "chromium" => Ok(Preset::Chrome),
+            "iron" => Ok(Preset::Iron),
+            "plastic" => Ok(Preset::Plastic),
+            "rubber" => Ok(Preset::Rubber),
+            _ => Err(format!("Unknown material preset: {}", name))
+        }
+    }
+}
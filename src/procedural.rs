@@ -0,0 +1,96 @@
+use crate::vec3::Vec3;
+use crate::hit::HitRecord;
+use crate::noise::{Basis, fbm, turbulence, worley};
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+/// Coordinates a procedural texture is evaluated in. `Uv` uses the surface
+/// coordinates as `(u, v, 0)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Space {
+    Object,
+    World,
+    Uv
+}
+
+/// Scalar pattern in `[0, 1]`, blending between the two colors of the
+/// texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// Alternating unit cubes.
+    Checker,
+    /// Alternating unit slabs along `x`.
+    Stripes,
+    /// Linear ramp from 0 at `x = 0` to 1 at `x = 1`.
+    Gradient,
+    Noise { basis: Basis, octaves: u32 },
+    Turbulence { basis: Basis, octaves: u32 },
+    /// Distance to the closest cell center.
+    Worley,
+    /// Veins along `x` distorted by turbulence.
+    Marble { turbulence: f64, octaves: u32 },
+    /// Concentric rings around the `y` axis, `rings` per unit.
+    Wood { rings: f64, turbulence: f64 }
+}
+
+/// Pattern evaluated at the hit point after scaling and offsetting the
+/// coordinates, `p * scale + offset`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Procedural {
+    pub pattern: Pattern,
+    pub colors: (Color, Color),
+    pub space: Space,
+    pub scale: Vec3,
+    pub offset: Vec3
+}
+
+impl Procedural {
+    pub fn new(pattern: Pattern, colors: (Color, Color)) -> Procedural {
+        Procedural {
+            pattern,
+            colors,
+            space: Space::Object,
+            scale: Vec3::new(1.0, 1.0, 1.0, false),
+            offset: Vec3::new(0.0, 0.0, 0.0, false)
+        }
+    }
+
+    pub fn value(&self, hit_record: &HitRecord) -> Color {
+        let point = match self.space {
+            Space::Object => hit_record.object_point,
+            Space::World => hit_record.point,
+            Space::Uv => Point3::new(hit_record.uv.0, hit_record.uv.1, 0.0, true)
+        };
+
+        let t = self.pattern.eval(point * self.scale + self.offset).clamp(0.0, 1.0);
+
+        self.colors.0 * (1.0 - t) + self.colors.1 * t
+    }
+}
+
+fn parity(x: f64) -> f64 {
+    x.floor().rem_euclid(2.0)
+}
+
+impl Pattern {
+    pub fn eval(&self, p: Point3) -> f64 {
+        match *self {
+            Pattern::Checker => parity(p.x().floor() + p.y().floor() + p.z().floor()),
+            Pattern::Stripes => parity(p.x()),
+            Pattern::Gradient => p.x(),
+            Pattern::Noise { basis, octaves } => 0.5 * (fbm(basis, p, octaves) + 1.0),
+            Pattern::Turbulence { basis, octaves } => turbulence(basis, p, octaves),
+            Pattern::Worley => worley(p),
+            Pattern::Marble { turbulence: amount, octaves } => {
+                let distortion = amount * turbulence(Basis::Perlin, p, octaves);
+                0.5 * (1.0 + (std::f64::consts::PI * (p.x() + distortion)).sin())
+            }
+            Pattern::Wood { rings, turbulence: amount } => {
+                let distortion = amount * fbm(Basis::Perlin, p, 2);
+                let radius = (p.x() * p.x() + p.z() * p.z()).sqrt() + distortion;
+                (radius * rings).rem_euclid(1.0)
+            }
+        }
+    }
+}
@@ -18,7 +18,7 @@ impl<'a, W: Write> ProgressBar<'a, W> {
     }
 
     pub fn increment(&mut self, step: usize) {
-        self.complete = self.complete + step;
+        self.complete += step;
 
         let complete_length = (self.complete * self.length) / self.total;
         let incomplete_length = self.length - complete_length;
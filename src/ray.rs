@@ -3,16 +3,75 @@ use crate::transform::Transform;
 
 use Vec3 as Point3;
 
+/// Distance hits are searched from. Rays leaving a surface have their origin
+/// pushed off it, so only hits at the origin itself are ignored.
+pub const RAY_T_MIN: f64 = f64::MIN_POSITIVE;
+
+/// Two auxiliary rays offset from the main one by a pixel along the x and
+/// y axes of the image, used to estimate texture footprints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Differentials {
+    pub rx_origin: Point3,
+    pub rx_direction: Vec3,
+    pub ry_origin: Point3,
+    pub ry_direction: Vec3
+}
+
+impl Differentials {
+    /// Differentials of a ray mirrored at `point` about `normal`, with the
+    /// auxiliary rays leaving from `point + dpdx` and `point + dpdy`. The
+    /// curvature of the surface is ignored.
+    pub fn reflected(&self, point: Point3, normal: Vec3, dpdx: Vec3, dpdy: Vec3) -> Differentials {
+        Differentials {
+            rx_origin: point + dpdx,
+            rx_direction: self.rx_direction.reflect(normal),
+            ry_origin: point + dpdy,
+            ry_direction: self.ry_direction.reflect(normal)
+        }
+    }
+
+    /// Differentials of a ray refracted at `point`, `eta` being the ratio of
+    /// the incident to the transmitted index of refraction.
+    pub fn refracted(&self, point: Point3, normal: Vec3, eta: f64, dpdx: Vec3, dpdy: Vec3) -> Differentials {
+        let refract = |direction: Vec3| direction.refract(normal, eta).unwrap_or_else(|| direction.normalized().reflect(normal));
+
+        Differentials {
+            rx_origin: point + dpdx,
+            rx_direction: refract(self.rx_direction),
+            ry_origin: point + dpdy,
+            ry_direction: refract(self.ry_direction)
+        }
+    }
+
+    /// Differentials of a ray continuing in the same direction from `point`.
+    pub fn transmitted(&self, point: Point3, dpdx: Vec3, dpdy: Vec3) -> Differentials {
+        Differentials {
+            rx_origin: point + dpdx,
+            ry_origin: point + dpdy,
+            ..*self
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
-    direction_inv: Vec3
+    direction_inv: Vec3,
+    differentials: Option<Differentials>
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Ray {
-        Ray { origin, direction, direction_inv: 1.0 / direction }
+        Ray { origin, direction, direction_inv: 1.0 / direction, differentials: None }
+    }
+
+    pub fn with_differentials(origin: Point3, direction: Vec3, differentials: Option<Differentials>) -> Ray {
+        Ray { differentials, ..Ray::new(origin, direction) }
+    }
+
+    pub fn differentials(&self) -> Option<Differentials> {
+        self.differentials
     }
 
     pub fn origin(&self) -> Point3 {
@@ -39,11 +98,18 @@ impl Transform for Ray {
         let new_origin = self.origin.transform(matrix);
         let new_direction = self.direction.transform(matrix);
         let new_direction_inv = 1.0 / new_direction;
+        let new_differentials = self.differentials.map(|differentials| Differentials {
+            rx_origin: differentials.rx_origin.transform(matrix),
+            rx_direction: differentials.rx_direction.transform(matrix),
+            ry_origin: differentials.ry_origin.transform(matrix),
+            ry_direction: differentials.ry_direction.transform(matrix)
+        });
 
         Self::Output {
             origin: new_origin,
             direction: new_direction,
-            direction_inv: new_direction_inv
+            direction_inv: new_direction_inv,
+            differentials: new_differentials
         }
     }
 }
@@ -1,22 +1,34 @@
+use rand::Rng;
+
 use crate::vec3::Vec3;
-use crate::ray::Ray;
-use crate::hit::{HitRecord, Hit};
-use crate::brdf::{brdf, perturb};
+use crate::ray::{Ray, RAY_T_MIN};
+use crate::hit::{HitRecord, Hit, RayKind};
+use crate::material::Material;
+use crate::brdf::{brdf, pdf, g1, sample_brdf_lobe, Lobe, perturb, fresnel_dielectric, sample_microfacet_normal};
 use crate::transform::Transform;
+use crate::frame::Frame;
 use crate::math::transpose;
-use crate::light::Light;
+use crate::scene::{Scene, occluded};
+use crate::medium::MediumStack;
+use crate::multiscatter::dielectric_albedo;
+use crate::subsurface::{VolumeEvent, transmittance, sample_isotropic};
+use crate::bump::shading_normal;
 
+use Vec3 as Point3;
 use Vec3 as Color;
 
 const LIGHT_SAMPLES_COUNT: i32 = 4;
 const REFLECT_SAMPLES_COUNT: i32 = 4;
+const TRANSMIT_SAMPLES_COUNT: i32 = 4;
+const SUBSURFACE_SAMPLES_COUNT: i32 = 4;
+const MAX_WALK_STEPS: i32 = 256;
 
-fn intersect_world(world: &Vec<Box<dyn Hit>>, ray: &Ray) -> Option<HitRecord> {
-    let t_min: f64 = 0.001;
+fn intersect_world<'a, I: IntoIterator<Item = &'a Box<dyn Hit>>>(objects: I, ray: &Ray) -> Option<HitRecord<'a>> {
+    let t_min: f64 = RAY_T_MIN;
     let mut t_max: f64 = f64::INFINITY;
     let mut hit_record: Option<HitRecord> = None;
 
-    for object in world {
+    for object in objects {
         let mut t_ray = *ray;
 
         if let Some(transform_matrix) = object.transform_matrix() {
@@ -27,8 +39,12 @@ fn intersect_world(world: &Vec<Box<dyn Hit>>, ray: &Ray) -> Option<HitRecord> {
             t_max = record.t_min;
 
             if let Some(transform_matrix) = object.transform_matrix() {
-                record.point = record.point.transform(&transform_matrix.mat);
-                record.normal = record.normal.transform(&transpose(&transform_matrix.inv)).normalized();
+                record.transform_point(&transform_matrix.mat);
+                let normal_matrix = transpose(&transform_matrix.inv);
+                record.normal = record.normal.transform(&normal_matrix).normalized();
+                record.geometric_normal = record.geometric_normal.transform(&normal_matrix).normalized();
+                record.dpdu = record.dpdu.transform(&transform_matrix.mat);
+                record.dpdv = record.dpdv.transform(&transform_matrix.mat);
             }
 
             hit_record = Some(record);
@@ -38,42 +54,396 @@ fn intersect_world(world: &Vec<Box<dyn Hit>>, ray: &Ray) -> Option<HitRecord> {
     hit_record
 }
 
-pub fn trace_ray(world: &Vec<Box<dyn Hit>>, ray: &Ray, light: &Light, depth: i32) -> Color {
+/// Objects visible to rays of `kind`.
+fn visible_objects(scene: &Scene, kind: RayKind) -> impl Iterator<Item = &Box<dyn Hit>> {
+    scene.objects.iter().filter(move |object| object.visibility().is_visible(kind))
+}
+
+/// Light from the scene's lights, each sample picking one of the lights
+/// linked to the lit object in proportion to its estimated contribution.
+fn direct_lighting<F: Fn(Vec3) -> Color>(scene: &Scene, hit_record: &HitRecord, normal: Vec3, bsdf: F) -> Color {
+    let point = hit_record.point;
+    let mut rng = rand::thread_rng();
+    let mut direct_illumination = Color::new(0.0, 0.0, 0.0, false);
+
+    for _ in 0..LIGHT_SAMPLES_COUNT {
+        if let Some((light, pmf)) = scene.lights.sample_linked(hit_record.name, point, normal, rng.gen()) {
+            let sample = light.sample();
+            let light_dir = sample - point;
+            let origin = hit_record.spawn_origin(light_dir);
+            let shadow_ray = Ray::new(origin, sample - origin);
+            let casters = visible_objects(scene, RayKind::Shadow).filter(|object| light.shadow_linking.includes(object.name()));
+
+            // the light sample lies at the end of the shadow ray
+            if !occluded(casters, &shadow_ray, 1.0) {
+                direct_illumination = direct_illumination + bsdf(light_dir) * light.irradiance(sample, point, normal) / pmf;
+            }
+        }
+    }
+
+    direct_illumination / (LIGHT_SAMPLES_COUNT as f64)
+}
+
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    pdf * pdf / (pdf * pdf + other_pdf * other_pdf)
+}
+
+/// Light from an environment map or sky, sampled proportionally to its
+/// luminance and weighted against brdf sampling. `bsdf` returns the brdf
+/// and its sampling density for a direction.
+fn background_lighting<F: Fn(Vec3) -> (Color, f64)>(scene: &Scene, hit_record: &HitRecord, normal: Vec3, bsdf: F) -> Color {
+    let background = &scene.background;
+    let mut rng = rand::thread_rng();
+    let mut illumination = Color::new(0.0, 0.0, 0.0, false);
+
+    for _ in 0..LIGHT_SAMPLES_COUNT {
+        let (direction, light_pdf) = background.sample((rng.gen(), rng.gen()));
+        let cos_theta = normal.dot(direction);
+
+        if light_pdf <= 0.0 || cos_theta <= 0.0 {
+            continue;
+        }
+
+        let (value, bsdf_pdf) = bsdf(direction);
+
+        if !scene.occluded(&Ray::new(hit_record.spawn_origin(direction), direction), f64::INFINITY) {
+            let weight = power_heuristic(light_pdf, bsdf_pdf) * cos_theta / light_pdf;
+            illumination = illumination + value * background.value(direction) * weight;
+        }
+    }
+
+    illumination / (LIGHT_SAMPLES_COUNT as f64)
+}
+
+/// Radiance along a ray sampled from a brdf with density `pdf`. The
+/// background it may reach is weighted against background sampling.
+fn trace_brdf_sample(scene: &Scene, ray: &Ray, kind: RayKind, pdf: f64, depth: i32, media: &MediumStack) -> Color {
     if depth <= 0 {
-        return Color::new(0.08, 0.18, 0.29, false);
+        return Color::new(0.0, 0.0, 0.0, false);
+    }
+
+    match intersect_world(visible_objects(scene, kind), ray) {
+        Some(hit_record) => shade_hit(scene, ray, kind, hit_record, depth, media),
+        None => {
+            let light_pdf = scene.background.pdf(ray.direction());
+            scene.background.value(ray.direction()) * power_heuristic(pdf, light_pdf)
+        }
+    }
+}
+
+fn shade_opaque(scene: &Scene, ray: &Ray, hit_record: &HitRecord, depth: i32, media: &MediumStack) -> Color {
+    let material = hit_record.material;
+    let view_dir = -ray.direction();
+    let frame = hit_record.frame();
+
+    // directions below the geometric surface would leak light through it
+    let is_above = |direction: Vec3| direction.dot(hit_record.geometric_normal) > 0.0;
+
+    let mut direct_illumination = direct_lighting(scene, hit_record, hit_record.normal, |light_dir| {
+        if is_above(light_dir) { brdf(material, &frame, view_dir, light_dir) } else { Color::new(0.0, 0.0, 0.0, false) }
+    });
+
+    if scene.background.is_sampled() {
+        direct_illumination = direct_illumination + background_lighting(scene, hit_record, hit_record.normal, |light_dir| {
+            if is_above(light_dir) {
+                (brdf(material, &frame, view_dir, light_dir), pdf(material, &frame, view_dir, light_dir))
+            } else {
+                (Color::new(0.0, 0.0, 0.0, false), 0.0)
+            }
+        });
+    }
+
+    let mut indirect_illumination = Color::new(0.0, 0.0, 0.0, false);
+
+    for _ in 0..REFLECT_SAMPLES_COUNT {
+        if let Some((direction, lobe)) = sample_brdf_lobe(material, &frame, view_dir) {
+            let pdf = pdf(material, &frame, view_dir, direction);
+
+            if pdf > 0.0 && is_above(direction) {
+                let weight = brdf(material, &frame, view_dir, direction) * (frame.normal.dot(direction).max(0.0) / pdf);
+                // glossy differentials follow the mirror reflection about the
+                // half vector, diffuse bounces are too spread out to keep them
+                let half = (view_dir.normalized() + direction).normalized();
+                let differentials = ray.differentials()
+                    .filter(|_| lobe != Lobe::Diffuse)
+                    .map(|d| d.reflected(hit_record.point, half, hit_record.dpdx, hit_record.dpdy));
+                let reflect_ray = Ray::with_differentials(hit_record.spawn_origin(direction), direction, differentials);
+                let kind = if lobe == Lobe::Diffuse { RayKind::Indirect } else { RayKind::Reflection };
+                indirect_illumination = indirect_illumination + weight * trace_brdf_sample(scene, &reflect_ray, kind, pdf, depth - 1, media);
+            }
+        }
+    }
+
+    indirect_illumination = indirect_illumination / (REFLECT_SAMPLES_COUNT as f64);
+
+    direct_illumination + indirect_illumination
+}
+
+/// `boundary` is the unevaluated material of the surface, which identifies
+/// the medium on the other side.
+fn shade_transmissive(scene: &Scene, ray: &Ray, hit_record: &HitRecord, boundary: &Material, depth: i32, media: &MediumStack) -> Color {
+    let mut rng = rand::thread_rng();
+    let material = hit_record.material;
+    let incident = ray.direction().normalized();
+    let view_dir = -incident;
+    let frame = hit_record.frame();
+    let (alpha_x, alpha_z) = material.alpha();
+    let interface = media.interface(boundary, hit_record.front_face);
+    let eta = interface.eta();
+    let white = Color::new(1.0, 1.0, 1.0, false);
+    let black = Color::new(0.0, 0.0, 0.0, false);
+    let refracted_media = if material.thin_walled { media.clone() } else { media.crossed(boundary, hit_record.front_face) };
+
+    let mut transmitted_light = Color::new(0.0, 0.0, 0.0, false);
+
+    for _ in 0..TRANSMIT_SAMPLES_COUNT {
+        let microfacet_normal = if material.roughness > 0.0 {
+            sample_microfacet_normal(&frame, view_dir, alpha_x, alpha_z)
+        } else {
+            hit_record.normal
+        };
+        let cos_theta_i = view_dir.dot(microfacet_normal);
+
+        let reflectance = if material.thin_walled {
+            // a film is the whole wall, with the surrounding medium on both sides
+            match &material.thin_film {
+                Some(film) => film.reflectance(cos_theta_i, media.ior(), Color::new(media.ior(), media.ior(), media.ior(), false), black),
+                None => {
+                    let r = fresnel_dielectric(cos_theta_i, media.ior() / material.ior);
+                    let reflectance = 2.0 * r / (1.0 + r);
+                    Color::new(reflectance, reflectance, reflectance, false)
+                }
+            }
+        } else {
+            match &material.thin_film {
+                Some(film) => film.reflectance(cos_theta_i, interface.eta_i, Color::new(interface.eta_t, interface.eta_t, interface.eta_t, false), black),
+                None => {
+                    let reflectance = fresnel_dielectric(cos_theta_i, eta);
+                    Color::new(reflectance, reflectance, reflectance, false)
+                }
+            }
+        };
+
+        // choose between reflection and transmission proportionally to the
+        // fresnel term, so every sample of an uncoated interface carries full
+        // weight
+        let probability = (reflectance.x() + reflectance.y() + reflectance.z()) / 3.0;
+        let transmitted = if material.thin_walled {
+            Some(perturb(&incident, material.roughness))
+        } else {
+            incident.refract(microfacet_normal, eta)
+        };
+
+        let (direction, tint, is_reflection) = match transmitted {
+            Some(transmitted) if rng.gen::<f64>() >= probability => {
+                (transmitted, (white - reflectance) / (1.0 - probability) * material.albedo, false)
+            }
+            Some(_) => (incident.reflect(microfacet_normal), reflectance / probability, true),
+            None => (incident.reflect(microfacet_normal), white, true)
+        };
+
+        // rough microfacets may scatter to the wrong side of the surface
+        if is_reflection != (direction.dot(hit_record.geometric_normal) > 0.0) {
+            continue;
+        }
+
+        // visible normals only account for the masking of the view
+        // direction, the scattered direction may be shadowed as well
+        let tint = if material.roughness > 0.0 && (is_reflection || !material.thin_walled) {
+            let l = frame.to_local(direction);
+            tint * g1(alpha_x, alpha_z, Vec3::new(l.x(), l.y().abs(), l.z(), false))
+        } else {
+            tint
+        };
+
+        let differentials = ray.differentials().map(|d| {
+            let (point, dpdx, dpdy) = (hit_record.point, hit_record.dpdx, hit_record.dpdy);
+
+            if is_reflection {
+                d.reflected(point, microfacet_normal, dpdx, dpdy)
+            } else if material.thin_walled {
+                d.transmitted(point, dpdx, dpdy)
+            } else {
+                d.refracted(point, microfacet_normal, eta, dpdx, dpdy)
+            }
+        });
+        let scattered_ray = Ray::with_differentials(hit_record.spawn_origin(direction), direction, differentials);
+        let scattered_media = if is_reflection { media } else { &refracted_media };
+        transmitted_light = transmitted_light + tint * trace_path(scene, &scattered_ray, RayKind::Reflection, depth - 1, scattered_media);
+    }
+
+    transmitted_light = transmitted_light / (TRANSMIT_SAMPLES_COUNT as f64);
+
+    // rough interfaces lose the energy of paths that would bounce between
+    // microfacets, compensate with the precomputed albedo of the interface
+    if material.roughness > 0.0 && !material.thin_walled {
+        let cos_theta_v = view_dir.dot(frame.normal);
+        transmitted_light = transmitted_light / dielectric_albedo(cos_theta_v, (alpha_x * alpha_z).sqrt(), eta).max(0.1);
+    }
+
+    transmitted_light
+}
+
+/// Light leaving a scattering volume through `hit_record`, treating the
+/// interface as a diffuse transmitter facing away from the volume.
+fn shade_exit(scene: &Scene, hit_record: &HitRecord, depth: i32, media: &MediumStack) -> Color {
+    let normal = -hit_record.normal;
+    let frame = Frame::from_normal(normal);
+
+    let direct_illumination = direct_lighting(scene, hit_record, normal, |_| {
+        Color::new(1.0, 1.0, 1.0, false) / std::f64::consts::PI
+    });
+
+    let mut rng = rand::thread_rng();
+    let r = rng.gen::<f64>().sqrt();
+    let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+    let direction = frame.to_world(Vec3::new(r * phi.cos(), (1.0 - r * r).max(0.0).sqrt(), r * phi.sin(), false));
+    let exit_ray = Ray::new(hit_record.spawn_origin(direction), direction);
+
+    direct_illumination + trace_path(scene, &exit_ray, RayKind::Indirect, depth - 1, media)
+}
+
+/// Follows light refracted into a closed scattering object until it leaves
+/// the surface again, returning the radiance carried along the walk.
+fn random_walk(scene: &Scene, entry: Point3, direction: Vec3, material: &Material, depth: i32, media: &MediumStack) -> Color {
+    let subsurface = match &material.scattering {
+        Some(subsurface) => subsurface,
+        None => return Color::new(0.0, 0.0, 0.0, false)
+    };
+    let mut rng = rand::thread_rng();
+    let mut origin = entry;
+    let mut direction = direction.normalized();
+    let mut throughput = Color::new(1.0, 1.0, 1.0, false);
+
+    for _ in 0..MAX_WALK_STEPS {
+        let walk_ray = Ray::new(origin, direction);
+
+        // a walk escaping without hitting anything means the object is open
+        let boundary = match intersect_world(&scene.objects, &walk_ray) {
+            Some(boundary) => boundary,
+            None => break
+        };
+
+        match subsurface.sample_event(boundary.t_min) {
+            VolumeEvent::Scatter { distance, weight } => {
+                throughput = throughput * weight;
+                origin = walk_ray.at(distance);
+                direction = sample_isotropic();
+            }
+            VolumeEvent::Pass { weight } => {
+                throughput = throughput * weight;
+
+                // the boundary normal faces the walk, i.e. points inwards
+                let reflectance = fresnel_dielectric(-direction.dot(boundary.normal), material.ior);
+
+                if rng.gen::<f64>() < reflectance {
+                    direction = direction.reflect(boundary.normal);
+                    origin = boundary.spawn_origin(direction);
+                    continue;
+                }
+
+                return throughput * shade_exit(scene, &boundary, depth, media);
+            }
+        }
     }
 
-    if let Some(hit_record) = intersect_world(world, ray) {
-        let view_dir = -ray.direction();
+    Color::new(0.0, 0.0, 0.0, false)
+}
 
-        let mut direct_illumination = Color::new(0.0, 0.0, 0.0, false);
+fn shade_subsurface(scene: &Scene, ray: &Ray, hit_record: &HitRecord, depth: i32, media: &MediumStack) -> Color {
+    let mut rng = rand::thread_rng();
+    let ior = hit_record.material.ior;
+    let incident = ray.direction().normalized();
+    let reflectance = fresnel_dielectric(-incident.dot(hit_record.normal), 1.0 / ior);
 
-        for _ in 0..LIGHT_SAMPLES_COUNT {
-            let light_dir = light.sample() - hit_record.point;
-            let brdf = brdf(hit_record.material, hit_record.normal, view_dir, light_dir);
-            let shadow_ray = Ray::new(hit_record.point, light_dir);
+    let mut scattered_light = Color::new(0.0, 0.0, 0.0, false);
 
-            if let None = intersect_world(world, &shadow_ray) {
-                direct_illumination = direct_illumination + brdf * light.color * hit_record.normal.dot(light_dir).max(0.0);
+    for _ in 0..SUBSURFACE_SAMPLES_COUNT {
+        let sample = match incident.refract(hit_record.normal, 1.0 / ior) {
+            Some(refracted) if rng.gen::<f64>() >= reflectance => {
+                random_walk(scene, hit_record.spawn_origin(refracted), refracted, hit_record.material, depth, media)
+            }
+            _ => {
+                let reflected = incident.reflect(hit_record.normal);
+                let reflect_ray = Ray::new(hit_record.spawn_origin(reflected), reflected);
+                trace_path(scene, &reflect_ray, RayKind::Reflection, depth - 1, media)
             }
+        };
+
+        scattered_light = scattered_light + sample;
+    }
+
+    scattered_light / (SUBSURFACE_SAMPLES_COUNT as f64)
+}
+
+/// Radiance leaving the surface at `hit_record` towards the origin of `ray`.
+fn shade_hit(scene: &Scene, ray: &Ray, kind: RayKind, mut hit_record: HitRecord, depth: i32, media: &MediumStack) -> Color {
+    hit_record.compute_differentials(ray);
+
+    let material = hit_record.material;
+    let continued_ray = Ray::with_differentials(
+        hit_record.spawn_origin(ray.direction()),
+        ray.direction(),
+        ray.differentials().map(|d| d.transmitted(hit_record.point, hit_record.dpdx, hit_record.dpdy))
+    );
+    let is_medium_boundary = material.transmission > 0.0 && !material.thin_walled;
+
+    let mut color = if is_medium_boundary && media.is_false_boundary(material, hit_record.front_face) {
+        // the boundary lies inside a medium of higher priority
+        trace_path(scene, &continued_ray, kind, depth, &media.crossed(material, hit_record.front_face))
+    } else if material.scattering.is_some() {
+        if hit_record.front_face {
+            shade_subsurface(scene, ray, &hit_record, depth, media)
+        } else {
+            // paths only enter scattering objects through refraction
+            trace_path(scene, &continued_ray, kind, depth, media)
         }
+    } else {
+        let mut surface_color = Color::new(0.0, 0.0, 0.0, false);
 
-        direct_illumination = direct_illumination / (LIGHT_SAMPLES_COUNT as f64);
+        // shade with the textured parameters and normal evaluated at the hit
+        let view_dir = -ray.direction().normalized();
+        let surface = material.evaluate(&hit_record, view_dir);
+        let shading_record = HitRecord {
+            normal: shading_normal(&surface, &hit_record, view_dir),
+            material: &surface,
+            ..hit_record
+        };
+        let transmission = surface.transmission_weight();
 
-        let reflect_dir = ray.direction().reflect(hit_record.normal);
-        let mut indirect_illumination = Color::new(0.0, 0.0, 0.0, false);
+        if transmission < 1.0 {
+            surface_color = surface_color + shade_opaque(scene, ray, &shading_record, depth, media) * (1.0 - transmission);
+        }
 
-        for _ in 0..REFLECT_SAMPLES_COUNT {
-            let direction = perturb(&reflect_dir, hit_record.material.roughness);
-            let reflect_ray = Ray::new(hit_record.point, direction);
-            indirect_illumination = indirect_illumination + trace_ray(world, &reflect_ray, light, depth - 1);
+        if transmission > 0.0 {
+            surface_color = surface_color + shade_transmissive(scene, ray, &shading_record, material, depth, media) * transmission;
         }
 
-        indirect_illumination = indirect_illumination / (REFLECT_SAMPLES_COUNT as f64);
+        surface_color
+    };
+
+    // the segment leading up to the hit travelled through the current medium
+    if let Some(medium) = media.current() {
+        let distance = hit_record.t_min * ray.direction().length();
+        color = color * transmittance(medium.absorption, distance);
+    }
+
+    color
+}
 
-        return direct_illumination + indirect_illumination * hit_record.material.metallic;
+/// Radiance arriving along `ray`, a ray of `kind`, black once `depth`
+/// bounces are used up.
+fn trace_path(scene: &Scene, ray: &Ray, kind: RayKind, depth: i32, media: &MediumStack) -> Color {
+    if depth <= 0 {
+        return Color::new(0.0, 0.0, 0.0, false);
     }
 
-    Color::new(0.08, 0.18, 0.29, false)
+    match intersect_world(visible_objects(scene, kind), ray) {
+        Some(hit_record) => shade_hit(scene, ray, kind, hit_record, depth, media),
+        None => scene.background.value(ray.direction())
+    }
 }
 
+pub fn trace_ray(scene: &Scene, ray: &Ray, depth: i32) -> Color {
+    trace_path(scene, ray, RayKind::Camera, depth, &MediumStack::new())
+}
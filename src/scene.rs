@@ -0,0 +1,42 @@
+use crate::hit::{Hit, RayKind};
+use crate::ray::{Ray, RAY_T_MIN};
+use crate::transform::Transform;
+use crate::light::Light;
+use crate::lighttree::LightTree;
+use crate::background::Background;
+
+/// Everything a ray can interact with: the objects, the lights and the
+/// background seen by rays escaping the scene.
+pub struct Scene {
+    pub objects: Vec<Box<dyn Hit>>,
+    pub lights: LightTree,
+    pub background: Background
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Box<dyn Hit>>, lights: Vec<Light>) -> Scene {
+        Scene {
+            objects,
+            lights: LightTree::new(lights),
+            background: Background::default()
+        }
+    }
+
+    /// Whether an object casting shadows lies along `ray` before `t_max`.
+    pub fn occluded(&self, ray: &Ray, t_max: f64) -> bool {
+        occluded(self.objects.iter().filter(|object| object.visibility().is_visible(RayKind::Shadow)), ray, t_max)
+    }
+}
+
+/// Whether any of `objects` lies along `ray` before `t_max`, stopping at
+/// the first one found.
+pub fn occluded<'a, I: IntoIterator<Item = &'a Box<dyn Hit>>>(objects: I, ray: &Ray, t_max: f64) -> bool {
+    objects.into_iter().any(|object| {
+        let t_ray = match object.transform_matrix() {
+            Some(transform_matrix) => ray.transform(&transform_matrix.inv),
+            None => *ray
+        };
+
+        object.occluded(&t_ray, RAY_T_MIN, t_max)
+    })
+}
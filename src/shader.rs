@@ -0,0 +1,337 @@
+use crate::vec3::Vec3;
+use crate::hit::HitRecord;
+use crate::brdf::fresnel_dielectric;
+use crate::texture::{Texture, TextureCache, WrapMode};
+use crate::image::ColorSpace;
+use crate::procedural::{Procedural, Pattern, Space};
+use crate::noise::Basis;
+
+use Vec3 as Color;
+
+/// Node of a shader graph, evaluated per hit to a color. Scalar inputs such
+/// as mix factors read the first channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Constant(Color),
+    Texture(Texture),
+    /// Hit point in the given space, or `(u, v, 0)`.
+    Position(Space),
+    /// Shading normal in world space.
+    Normal,
+    /// Cosine between the normal and the view direction.
+    Facing,
+    /// Dielectric Fresnel reflectance for the given index of refraction.
+    Fresnel(f64),
+    /// Linear blend of the first two inputs by the third.
+    Mix(Box<Node>, Box<Node>, Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Multiply(Box<Node>, Box<Node>),
+    /// One minus the input.
+    Invert(Box<Node>),
+    /// Color ramp through `(position, color)` stops sorted by position.
+    Ramp(Box<Node>, Vec<(f64, Color)>)
+}
+
+fn gray(value: f64) -> Color {
+    Color::new(value, value, value, false)
+}
+
+impl Node {
+    pub fn constant(value: f64) -> Node {
+        Node::Constant(gray(value))
+    }
+
+    pub fn mix(a: Node, b: Node, factor: Node) -> Node {
+        Node::Mix(Box::new(a), Box::new(b), Box::new(factor))
+    }
+
+    /// `view` is the direction from the hit towards the viewer.
+    pub fn eval(&self, hit_record: &HitRecord, view: Vec3) -> Color {
+        match self {
+            Node::Constant(color) => *color,
+            Node::Texture(texture) => texture.value(hit_record, view),
+            Node::Position(Space::Object) => hit_record.object_point,
+            Node::Position(Space::World) => hit_record.point,
+            Node::Position(Space::Uv) => Color::new(hit_record.uv.0, hit_record.uv.1, 0.0, false),
+            Node::Normal => hit_record.normal,
+            Node::Facing => gray(hit_record.normal.dot(view.normalized()).abs()),
+            Node::Fresnel(ior) => gray(fresnel_dielectric(hit_record.normal.dot(view.normalized()).abs(), 1.0 / ior)),
+            Node::Mix(a, b, factor) => {
+                let t = factor.eval(hit_record, view).x().clamp(0.0, 1.0);
+                a.eval(hit_record, view) * (1.0 - t) + b.eval(hit_record, view) * t
+            }
+            Node::Add(a, b) => a.eval(hit_record, view) + b.eval(hit_record, view),
+            Node::Multiply(a, b) => a.eval(hit_record, view) * b.eval(hit_record, view),
+            Node::Invert(a) => gray(1.0) - a.eval(hit_record, view),
+            Node::Ramp(input, stops) => ramp(stops, input.eval(hit_record, view).x())
+        }
+    }
+}
+
+fn ramp(stops: &[(f64, Color)], t: f64) -> Color {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return gray(0.0)
+    };
+
+    if t <= first.0 {
+        return first.1;
+    }
+
+    for pair in stops.windows(2) {
+        let ((p0, c0), (p1, c1)) = (pair[0], pair[1]);
+
+        if t <= p1 {
+            let s = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+            return c0 * (1.0 - s) + c1 * s;
+        }
+    }
+
+    last.1
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Text(String),
+    Call(String, Vec<Expr>)
+}
+
+/// Deepest nesting of calls accepted, keeping the recursive parser and the
+/// evaluation of the graph within the stack.
+const MAX_NESTING: usize = 64;
+
+struct Parser<'a> {
+    source: &'a [u8],
+    position: usize,
+    depth: usize
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.source.len() && self.source[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.source.get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at offset {}", byte as char, self.position))
+        }
+    }
+
+    fn take_while<F: Fn(u8) -> bool>(&mut self, accept: F) -> String {
+        let start = self.position;
+
+        while self.position < self.source.len() && accept(self.source[self.position]) {
+            self.position += 1;
+        }
+
+        String::from_utf8_lossy(&self.source[start..self.position]).into_owned()
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(b'"') => {
+                self.position += 1;
+                let text = self.take_while(|byte| byte != b'"');
+                self.expect(b'"')?;
+                Ok(Expr::Text(text))
+            }
+            Some(byte) if byte.is_ascii_digit() || byte == b'-' || byte == b'.' => {
+                let number = self.take_while(|byte| byte.is_ascii_digit() || b"+-.eE".contains(&byte));
+                number.parse().map(Expr::Number).map_err(|_| format!("invalid number '{}'", number))
+            }
+            Some(byte) if byte.is_ascii_alphabetic() => {
+                let name = self.take_while(|byte| byte.is_ascii_alphanumeric() || byte == b'_');
+                let mut args = Vec::new();
+
+                if self.peek() == Some(b'(') {
+                    self.position += 1;
+                    self.depth += 1;
+
+                    if self.depth > MAX_NESTING {
+                        return Err(format!("nesting deeper than {} calls at offset {}", MAX_NESTING, self.position));
+                    }
+
+                    if self.peek() != Some(b')') {
+                        args.push(self.expr()?);
+
+                        while self.peek() == Some(b',') {
+                            self.position += 1;
+                            args.push(self.expr()?);
+                        }
+                    }
+
+                    self.expect(b')')?;
+                    self.depth -= 1;
+                }
+
+                Ok(Expr::Call(name, args))
+            }
+            _ => Err(format!("unexpected input at offset {}", self.position))
+        }
+    }
+}
+
+fn number(expr: &Expr) -> Result<f64, String> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        _ => Err(format!("expected a number, found {:?}", expr))
+    }
+}
+
+fn keyword(expr: &Expr) -> Result<&str, String> {
+    match expr {
+        Expr::Call(name, args) if args.is_empty() => Ok(name),
+        _ => Err(format!("expected a keyword, found {:?}", expr))
+    }
+}
+
+fn constant_color(expr: &Expr) -> Result<Color, String> {
+    match to_node(expr, &mut TextureCache::new())? {
+        Node::Constant(color) => Ok(color),
+        _ => Err(format!("expected a constant color, found {:?}", expr))
+    }
+}
+
+/// Black to white pattern in object space, with an optional uniform scale
+/// as first argument followed by the pattern's own parameters.
+fn pattern(name: &str, args: &[Expr]) -> Result<Node, String> {
+    let arg = |i: usize, default: f64| args.get(i).map_or(Ok(default), number);
+    let octaves = |i: usize| arg(i, 4.0).map(|octaves| octaves.max(1.0) as u32);
+
+    let pattern = match name {
+        "checker" => Pattern::Checker,
+        "stripes" => Pattern::Stripes,
+        "gradient" => Pattern::Gradient,
+        "worley" => Pattern::Worley,
+        "noise" => Pattern::Noise { basis: Basis::Perlin, octaves: octaves(1)? },
+        "turbulence" => Pattern::Turbulence { basis: Basis::Perlin, octaves: octaves(1)? },
+        "marble" => Pattern::Marble { turbulence: arg(1, 5.0)?, octaves: octaves(2)? },
+        "wood" => Pattern::Wood { rings: arg(1, 4.0)?, turbulence: arg(2, 0.1)? },
+        _ => return Err(format!("unknown node '{}'", name))
+    };
+
+    let scale = arg(0, 1.0)?;
+
+    Ok(Node::Texture(Texture::Procedural(Procedural {
+        scale: Vec3::new(scale, scale, scale, false),
+        ..Procedural::new(pattern, (gray(0.0), gray(1.0)))
+    })))
+}
+
+fn to_node(expr: &Expr, cache: &mut TextureCache) -> Result<Node, String> {
+    let (name, args) = match expr {
+        Expr::Number(value) => return Ok(Node::constant(*value)),
+        Expr::Text(text) => return Err(format!("unexpected string \"{}\"", text)),
+        Expr::Call(name, args) => (name.as_str(), args.as_slice())
+    };
+
+    let arity = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(format!("'{}' takes {} arguments, found {}", name, count, args.len()))
+        }
+    };
+    let mut input = |i: usize| to_node(&args[i], cache).map(Box::new);
+
+    match name {
+        "rgb" => {
+            arity(3)?;
+            Ok(Node::Constant(Color::new(number(&args[0])?, number(&args[1])?, number(&args[2])?, false)))
+        }
+        "position" => match args.first().map(keyword).transpose()? {
+            None | Some("object") => Ok(Node::Position(Space::Object)),
+            Some("world") => Ok(Node::Position(Space::World)),
+            Some("uv") => Ok(Node::Position(Space::Uv)),
+            Some(space) => Err(format!("unknown space '{}'", space))
+        },
+        "uv" => Ok(Node::Position(Space::Uv)),
+        "normal" => Ok(Node::Normal),
+        "facing" => Ok(Node::Facing),
+        "fresnel" => {
+            arity(1)?;
+            let ior = number(&args[0])?;
+            if ior <= 0.0 {
+                return Err(format!("'{}' needs a positive index of refraction, found {}", name, ior));
+            }
+            Ok(Node::Fresnel(ior))
+        }
+        "mix" => {
+            arity(3)?;
+            Ok(Node::Mix(input(0)?, input(1)?, input(2)?))
+        }
+        "add" => {
+            arity(2)?;
+            Ok(Node::Add(input(0)?, input(1)?))
+        }
+        "multiply" => {
+            arity(2)?;
+            Ok(Node::Multiply(input(0)?, input(1)?))
+        }
+        "invert" => {
+            arity(1)?;
+            Ok(Node::Invert(input(0)?))
+        }
+        "ramp" => {
+            if args.len() < 3 || args.len() % 2 == 0 {
+                return Err("'ramp' takes an input followed by position and color pairs".to_string());
+            }
+
+            let mut stops = args[1..].chunks_exact(2)
+                .map(|stop| Ok((number(&stop[0])?, constant_color(&stop[1])?)))
+                .collect::<Result<Vec<_>, String>>()?;
+            stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            Ok(Node::Ramp(input(0)?, stops))
+        }
+        "image" => {
+            let path = match args.first() {
+                Some(Expr::Text(path)) => path,
+                _ => return Err("'image' takes a path as first argument".to_string())
+            };
+            let mut color_space = ColorSpace::Srgb;
+            let mut wrap = WrapMode::Repeat;
+
+            for option in &args[1..] {
+                match keyword(option)? {
+                    "srgb" => color_space = ColorSpace::Srgb,
+                    "linear" => color_space = ColorSpace::Linear,
+                    "repeat" => wrap = WrapMode::Repeat,
+                    "mirror" => wrap = WrapMode::Mirror,
+                    "clamp" => wrap = WrapMode::Clamp,
+                    other => return Err(format!("unknown image option '{}'", other))
+                }
+            }
+
+            let image = cache.load(path, color_space).map_err(|err| format!("cannot load '{}': {}", path, err))?;
+
+            Ok(Node::Texture(Texture::image(image, wrap)))
+        }
+        _ => pattern(name, args)
+    }
+}
+
+/// Parses a graph written as nested calls, e.g.
+/// `mix(rgb(0.8, 0.1, 0.1), image("wood.png"), ramp(noise(4), 0.3, 0, 0.7, 1))`.
+/// Images are loaded through `cache`.
+pub fn parse(source: &str, cache: &mut TextureCache) -> Result<Node, String> {
+    let mut parser = Parser { source: source.as_bytes(), position: 0, depth: 0 };
+    let expr = parser.expr()?;
+
+    if parser.peek().is_some() {
+        return Err(format!("unexpected input at offset {}", parser.position));
+    }
+
+    to_node(&expr, cache)
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use crate::vec3::Vec3;
+use crate::image::Image;
+use crate::background::EnvironmentMap;
+use crate::frame::Frame;
+
+use Vec3 as Color;
+
+/// Angular radius of the sun seen from the ground, in degrees.
+pub const SUN_ANGULAR_RADIUS: f64 = 0.2665;
+/// Luminance of the sun outside the atmosphere, in kcd/m².
+const SUN_LUMINANCE: f64 = 2.0e6;
+/// Resolution of the map the sky dome is sampled from.
+const SAMPLING_WIDTH: usize = 128;
+const SAMPLING_HEIGHT: usize = 64;
+/// Wavelengths, in micrometers, the sun's transmittance is evaluated at.
+const WAVELENGTHS: [f64; 3] = [0.610, 0.550, 0.465];
+
+/// Perez distribution coefficients.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Perez([f64; 5]);
+
+impl Perez {
+    /// Relative luminance at zenith angle `theta` and angle `gamma` to the sun.
+    fn eval(&self, theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.0;
+        let cos_theta = theta.cos().max(0.01);
+
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+fn polynomial(coefficients: [f64; 4], x: f64) -> f64 {
+    coefficients.iter().fold(0.0, |sum, coefficient| sum * x + coefficient)
+}
+
+/// Direction towards the sun for an elevation above the horizon and an
+/// azimuth measured from north (`-z`) towards east (`+x`), in degrees.
+pub fn sun_direction(elevation: f64, azimuth: f64) -> Vec3 {
+    let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+
+    Vec3::new(elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos(), false)
+}
+
+/// Approximate solar elevation and azimuth, in degrees, for a day of the
+/// year (1 to 365), a local solar time in hours and a latitude in degrees.
+pub fn solar_position(day: u32, hour: f64, latitude: f64) -> (f64, f64) {
+    let declination = (23.45_f64).to_radians() * (2.0 * std::f64::consts::PI * (284.0 + day as f64) / 365.0).sin();
+    let hour_angle = (15.0 * (hour - 12.0)).to_radians();
+    let latitude = latitude.to_radians();
+
+    let sin_elevation = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+
+    let cos_azimuth = (declination.sin() - sin_elevation * latitude.sin()) / (elevation.cos() * latitude.cos()).max(1e-9);
+    let azimuth = cos_azimuth.clamp(-1.0, 1.0).acos().to_degrees();
+
+    // mornings have the sun in the east, afternoons in the west
+    (elevation.to_degrees(), if hour_angle > 0.0 { 360.0 - azimuth } else { azimuth })
+}
+
+/// Preetham sky dome, in kcd/m².
+#[derive(Debug, Clone, PartialEq)]
+struct Dome {
+    sun: Vec3,
+    perez: [Perez; 3],
+    zenith: [f64; 3]
+}
+
+impl Dome {
+    fn new(sun: Vec3, turbidity: f64) -> Dome {
+        let t = turbidity;
+        let theta_s = sun.y().clamp(-1.0, 1.0).acos().min(std::f64::consts::FRAC_PI_2);
+
+        let perez = [
+            Perez([0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703]),
+            Perez([-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452]),
+            Perez([-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529])
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f64::consts::PI - 2.0 * theta_s);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |t2: [f64; 4], t1: [f64; 4], t0: [f64; 4]| {
+            t * t * polynomial(t2, theta_s) + t * polynomial(t1, theta_s) + polynomial(t0, theta_s)
+        };
+        let x = chromaticity([0.00166, -0.00375, 0.00209, 0.0], [-0.02903, 0.06377, -0.03202, 0.00394], [0.11693, -0.21196, 0.06052, 0.25886]);
+        let y = chromaticity([0.00275, -0.00610, 0.00317, 0.0], [-0.04214, 0.08970, -0.04153, 0.00516], [0.15346, -0.26756, 0.06670, 0.26688]);
+
+        Dome { sun, perez, zenith: [luminance, x, y] }
+    }
+
+    /// Linear sRGB radiance, black below the horizon or once the sun set.
+    fn radiance(&self, direction: Vec3) -> Color {
+        let direction = direction.normalized();
+
+        if direction.y() <= 0.0 || self.sun.y() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0, false);
+        }
+
+        let theta = direction.y().acos();
+        let theta_s = self.sun.y().acos();
+        let gamma = direction.dot(self.sun).clamp(-1.0, 1.0).acos();
+        let value = |i: usize| self.zenith[i] * self.perez[i].eval(theta, gamma) / self.perez[i].eval(0.0, theta_s);
+
+        // xyY chromaticity to XYZ, then to linear sRGB
+        let (luminance, x, y) = (value(0), value(1), value(2));
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+
+        Color::new(
+            (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+            (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+            (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+            false
+        )
+    }
+
+    /// Dome baked into a latitude-longitude map.
+    fn image(&self) -> Image {
+        let pi = std::f64::consts::PI;
+        let mut pixels = Vec::with_capacity(SAMPLING_WIDTH * SAMPLING_HEIGHT);
+
+        for y in 0..SAMPLING_HEIGHT {
+            let theta = pi * (y as f64 + 0.5) / SAMPLING_HEIGHT as f64;
+
+            for x in 0..SAMPLING_WIDTH {
+                let phi = 2.0 * pi * (x as f64 + 0.5) / SAMPLING_WIDTH as f64;
+                pixels.push(self.radiance(Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false)));
+            }
+        }
+
+        Image::new(SAMPLING_WIDTH, SAMPLING_HEIGHT, pixels)
+    }
+}
+
+/// Fraction of sunlight reaching the ground per channel, through Rayleigh
+/// scattering and aerosols.
+fn sun_transmittance(sun: Vec3, turbidity: f64) -> Color {
+    if sun.y() <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0, false);
+    }
+
+    let zenith_angle = sun.y().acos().to_degrees();
+    let optical_mass = 1.0 / (sun.y() + 0.15 * (93.885 - zenith_angle).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let channel = |lambda: f64| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * optical_mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * optical_mass).exp();
+        rayleigh * aerosol
+    };
+
+    Color::new(channel(WAVELENGTHS[0]), channel(WAVELENGTHS[1]), channel(WAVELENGTHS[2]), false)
+}
+
+/// Preetham analytic daylight model with a sun disk. `turbidity` ranges
+/// from 2 (very clear) to about 10 (hazy). Luminances in kcd/m² are scaled
+/// by `intensity` into scene units. The ground below the horizon is black,
+/// it is expected to be modeled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sky {
+    pub intensity: f64,
+    turbidity: f64,
+    dome: Dome,
+    sun_radiance: Color,
+    sampling: EnvironmentMap
+}
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Sky {
+        let sun = sun_direction(elevation, azimuth);
+        let dome = Dome::new(sun, turbidity);
+        let sampling = EnvironmentMap::new(Arc::new(dome.image()), 0.0, 1.0);
+
+        Sky {
+            intensity: 0.05,
+            turbidity,
+            dome,
+            sun_radiance: sun_transmittance(sun, turbidity) * SUN_LUMINANCE,
+            sampling
+        }
+    }
+
+    pub fn from_time(day: u32, hour: f64, latitude: f64, turbidity: f64) -> Sky {
+        let (elevation, azimuth) = solar_position(day, hour, latitude);
+
+        Sky::new(elevation, azimuth, turbidity)
+    }
+
+    /// Unit direction towards the center of the sun.
+    pub fn sun_direction(&self) -> Vec3 {
+        self.dome.sun
+    }
+
+    pub fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    fn cos_sun_radius() -> f64 {
+        SUN_ANGULAR_RADIUS.to_radians().cos()
+    }
+
+    /// Radiance of the sky and sun disk arriving from `direction`.
+    pub fn value(&self, direction: Vec3) -> Color {
+        let mut radiance = self.dome.radiance(direction);
+
+        if direction.normalized().dot(self.dome.sun) >= Sky::cos_sun_radius() {
+            radiance = radiance + self.sun_radiance;
+        }
+
+        radiance * self.intensity
+    }
+
+    /// Probability of sampling the sun rather than the dome.
+    fn sun_probability(&self) -> f64 {
+        if self.dome.sun.y() > 0.0 { 0.5 } else { 0.0 }
+    }
+
+    /// Direction drawn from the sun disk or, proportionally to its
+    /// luminance, from the dome, with its density with respect to solid
+    /// angle.
+    pub fn sample(&self, u: (f64, f64)) -> (Vec3, f64) {
+        let sun_probability = self.sun_probability();
+
+        let direction = if u.0 < sun_probability {
+            // uniform in the cone subtended by the sun
+            let u0 = u.0 / sun_probability;
+            let cos_theta = 1.0 - u0 * (1.0 - Sky::cos_sun_radius());
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * std::f64::consts::PI * u.1;
+            Frame::from_normal(self.dome.sun).to_world(Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin(), false))
+        } else {
+            self.sampling.sample(((u.0 - sun_probability) / (1.0 - sun_probability), u.1)).0
+        };
+
+        (direction, self.pdf(direction))
+    }
+
+    pub fn pdf(&self, direction: Vec3) -> f64 {
+        let sun_probability = self.sun_probability();
+        let cone = if direction.normalized().dot(self.dome.sun) >= Sky::cos_sun_radius() {
+            1.0 / (2.0 * std::f64::consts::PI * (1.0 - Sky::cos_sun_radius()))
+        } else {
+            0.0
+        };
+
+        sun_probability * cone + (1.0 - sun_probability) * self.sampling.pdf(direction)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::vec3::Vec3;
 use crate::ray::Ray;
-use crate::hit::{HitRecord, Hit};
+use crate::hit::{HitRecord, Hit, Visibility};
 use crate::material::Material;
 use crate::transform::TransformMatrix;
+use crate::math::GAMMA_5;
 
 use Vec3 as Point3;
 
@@ -10,7 +11,9 @@ pub struct Sphere {
     pub center: Point3,
     pub radius: f64,
     pub material: Material,
-    pub transform_matrix: Option<TransformMatrix>
+    pub transform_matrix: Option<TransformMatrix>,
+    pub name: String,
+    pub visibility: Visibility
 }
 
 impl Sphere {
@@ -19,48 +22,107 @@ impl Sphere {
             center,
             radius,
             material,
-            transform_matrix
+            transform_matrix,
+            name: String::new(),
+            visibility: Visibility::default()
         }
     }
+
+    /// Spherical mapping: `u` follows the longitude around the y axis and
+    /// `v` the latitude from the bottom pole (0) to the top one (1).
+    pub fn uv_at(&self, point: Point3) -> ((f64, f64), Vec3, Vec3) {
+        let pi = std::f64::consts::PI;
+        let p = point - self.center;
+        let phi = p.z().atan2(p.x()).rem_euclid(2.0 * pi);
+        let theta = (-p.y() / self.radius).clamp(-1.0, 1.0).acos();
+        let rho = (p.x() * p.x() + p.z() * p.z()).sqrt();
+
+        // the derivatives vanish at the poles, keep an arbitrary tangent plane
+        let (dpdu, dpdv) = if rho > 1e-9 * self.radius {
+            (
+                Vec3::new(-p.z(), 0.0, p.x(), false) * (2.0 * pi),
+                Vec3::new(-p.y() * p.x() / rho, rho, -p.y() * p.z() / rho, false) * pi
+            )
+        } else {
+            (Vec3::new(0.0, 0.0, 2.0 * pi * self.radius, false), Vec3::new(pi * self.radius, 0.0, 0.0, false))
+        };
+
+        ((phi / (2.0 * pi), theta / pi), dpdu, dpdv)
+    }
 }
 
 impl Hit for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin() - self.center;
         let a = ray.direction().length_squared();
         let half_b = ray.direction().dot(oc);
         let c = oc.length_squared() - self.radius * self.radius;
-        let discriminant = half_b * half_b - a * c;
+
+        // computed from the distance between the center and the ray's line,
+        // which keeps its precision for small spheres far from the origin
+        let to_line = oc - ray.direction() * (half_b / a);
+        let discriminant = a * (self.radius * self.radius - to_line.length_squared());
 
         if discriminant < 0.0 {
             return None;
         }
 
-        let sqrtd = discriminant.sqrt();
-        let mut t = (-half_b - sqrtd) / a;
-
-        if t < t_min || t > t_max {
-            t = (-half_b + sqrtd) / a;
+        // the root of largest magnitude first, then the other one from the
+        // product of the roots, so neither suffers from cancellation
+        let q = -(half_b + discriminant.sqrt().copysign(half_b));
+        let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+        let (near, far) = (t0.min(t1), t0.max(t1));
 
-            if t < t_min || t > t_max {
-                return None;
-            }
-        }
+        let t = if near >= t_min && near <= t_max {
+            near
+        } else if far >= t_min && far <= t_max {
+            far
+        } else {
+            return None;
+        };
 
-        let hit_point = ray.at(t);
+        // projected back onto the sphere to remove the error of `ray.at`
+        let offset = ray.at(t) - self.center;
+        let hit_point = self.center + Vec3::new(offset.x(), offset.y(), offset.z(), false) * (self.radius / offset.length());
         let normal = (hit_point - self.center) / self.radius;
         let front_face = ray.direction().dot(normal) < 0.0;
+        let (uv, dpdu, dpdv) = self.uv_at(hit_point);
 
         Some(HitRecord {
             t_min: t,
             point: hit_point,
+            // the reprojection's error grows with the center and radius
+            point_error: Vec3::new(
+                self.center.x().abs() + self.radius,
+                self.center.y().abs() + self.radius,
+                self.center.z().abs() + self.radius,
+                false
+            ) * GAMMA_5,
+            object_point: hit_point,
             normal: if front_face { normal } else { -normal },
+            geometric_normal: if front_face { normal } else { -normal },
+            uv,
+            dpdu,
+            dpdv,
+            dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+            dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+            duvdx: (0.0, 0.0),
+            duvdy: (0.0, 0.0),
             front_face,
-            material: self.material
+            material: &self.material,
+            name: &self.name
         })
     }
 
     fn transform_matrix(&self) -> Option<&TransformMatrix> {
         self.transform_matrix.as_ref()
     }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility
+    }
 }
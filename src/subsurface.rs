@@ -0,0 +1,105 @@
+use rand::Rng;
+
+use crate::vec3::Vec3;
+
+use Vec3 as Color;
+
+/// Parameters of a homogeneous scattering volume enclosed by a closed
+/// surface. `albedo` is the resulting multiple-scattering color as seen from
+/// outside and `mean_free_path` the average distance, per channel, that light
+/// travels before scattering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Subsurface {
+    pub albedo: Color,
+    pub mean_free_path: Color
+}
+
+/// Scattering event sampled along a ray inside the volume.
+pub enum VolumeEvent {
+    Scatter { distance: f64, weight: Color },
+    Pass { weight: Color }
+}
+
+fn invert_albedo(albedo: f64) -> f64 {
+    // Chiang et al. 2016, fit of the single-scattering albedo that produces
+    // the given multiple-scattering albedo under a random walk
+    let a = albedo.clamp(0.0, 0.999);
+    let x = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+
+    1.0 - x * x
+}
+
+fn extinction_channel(albedo: f64, mean_free_path: f64) -> f64 {
+    let a = albedo.clamp(0.0, 0.999);
+    let s = 1.9 - a + 3.5 * (a - 0.8) * (a - 0.8);
+
+    1.0 / (mean_free_path.max(1e-6) * s)
+}
+
+impl Subsurface {
+    pub fn new(albedo: Color, mean_free_path: Color) -> Subsurface {
+        Subsurface { albedo, mean_free_path }
+    }
+
+    pub fn single_scattering_albedo(&self) -> Color {
+        Color::new(
+            invert_albedo(self.albedo.x()),
+            invert_albedo(self.albedo.y()),
+            invert_albedo(self.albedo.z()),
+            false
+        )
+    }
+
+    /// Extinction coefficient per channel.
+    pub fn extinction(&self) -> Color {
+        Color::new(
+            extinction_channel(self.albedo.x(), self.mean_free_path.x()),
+            extinction_channel(self.albedo.y(), self.mean_free_path.y()),
+            extinction_channel(self.albedo.z(), self.mean_free_path.z()),
+            false
+        )
+    }
+
+    /// Samples the distance to the next scattering event, choosing the
+    /// channel uniformly and weighting by the average pdf of all channels.
+    /// `max_distance` is the distance to the enclosing surface.
+    pub fn sample_event(&self, max_distance: f64) -> VolumeEvent {
+        let mut rng = rand::thread_rng();
+        let sigma_t = self.extinction();
+        let channel = rng.gen_range(0..3);
+        let distance = -(1.0 - rng.gen::<f64>()).ln() / sigma_t[channel];
+
+        if distance >= max_distance {
+            let transmittance = transmittance(sigma_t, max_distance);
+            let pdf = (transmittance.x() + transmittance.y() + transmittance.z()) / 3.0;
+
+            return VolumeEvent::Pass { weight: transmittance / pdf.max(1e-12) };
+        }
+
+        let transmittance = transmittance(sigma_t, distance);
+        let density = sigma_t * transmittance;
+        let pdf = (density.x() + density.y() + density.z()) / 3.0;
+        let sigma_s = self.single_scattering_albedo() * sigma_t;
+
+        VolumeEvent::Scatter { distance, weight: sigma_s * transmittance / pdf.max(1e-12) }
+    }
+}
+
+/// Beer–Lambert transmittance over `distance` for a per-channel extinction.
+pub fn transmittance(sigma_t: Color, distance: f64) -> Color {
+    Color::new(
+        (-sigma_t.x() * distance).exp(),
+        (-sigma_t.y() * distance).exp(),
+        (-sigma_t.z() * distance).exp(),
+        false
+    )
+}
+
+pub fn sample_isotropic() -> Vec3 {
+    let mut rng = rand::thread_rng();
+    let z = 1.0 - 2.0 * rng.gen::<f64>();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+
+    Vec3::new(r * phi.cos(), r * phi.sin(), z, false)
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::vec3::Vec3;
+use crate::hit::HitRecord;
+use crate::image::{Image, ColorSpace};
+use crate::procedural::Procedural;
+use crate::shader::Node;
+
+use Vec3 as Color;
+
+/// How texture coordinates outside of `[0, 1]` are mapped back onto the
+/// image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Mirror,
+    Clamp
+}
+
+/// Source of a material parameter varying over the surface.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Texture {
+    Constant(Color),
+    Image(ImageTexture),
+    Procedural(Procedural),
+    Graph(Box<Node>)
+}
+
+/// Filter used when the hit carries a footprint. `Trilinear` blends the
+/// two MIP levels matching the footprint size, `Ewa` integrates an
+/// elliptical gaussian over the footprint for sharper results at grazing
+/// angles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Bilinear,
+    Trilinear,
+    Ewa
+}
+
+/// Longest to shortest axis ratio of EWA footprints, beyond which the short
+/// axis is widened to bound the number of texels read.
+const MAX_ANISOTROPY: f64 = 8.0;
+
+/// Image sampled at the hit's uv coordinates. The image is shared, several
+/// textures may point to the same one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageTexture {
+    pub image: Arc<Image>,
+    pub wrap: WrapMode,
+    pub filter: Filter
+}
+
+impl ImageTexture {
+    pub fn new(image: Arc<Image>, wrap: WrapMode) -> ImageTexture {
+        ImageTexture { image, wrap, filter: Filter::Trilinear }
+    }
+
+    fn wrap(&self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+
+        let i = match self.wrap {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size { i } else { 2 * size - 1 - i }
+            }
+            WrapMode::Clamp => i.clamp(0, size - 1)
+        };
+
+        i as usize
+    }
+
+    fn texel(&self, image: &Image, x: i64, y: i64) -> Color {
+        image.pixel(self.wrap(x, image.width), self.wrap(y, image.height))
+    }
+
+    /// Bilinear lookup with `v` pointing up the image, texel centers lying
+    /// at half-integer coordinates.
+    fn bilinear(&self, image: &Image, uv: (f64, f64)) -> Color {
+        let x = uv.0 * image.width as f64 - 0.5;
+        let y = (1.0 - uv.1) * image.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let texel = |dx: i64, dy: i64| self.texel(image, x0 as i64 + dx, y0 as i64 + dy);
+
+        (texel(0, 0) * (1.0 - tx) + texel(1, 0) * tx) * (1.0 - ty)
+            + (texel(0, 1) * (1.0 - tx) + texel(1, 1) * tx) * ty
+    }
+
+    /// Bilinear lookup in the full resolution image.
+    pub fn sample(&self, uv: (f64, f64)) -> Color {
+        self.bilinear(&self.image, uv)
+    }
+
+    /// Lookup filtered over the footprint spanned by the uv differentials.
+    pub fn sample_footprint(&self, uv: (f64, f64), duvdx: (f64, f64), duvdy: (f64, f64)) -> Color {
+        let size = self.image.width.max(self.image.height) as f64;
+        let length = |d: (f64, f64)| (d.0 * d.0 + d.1 * d.1).sqrt();
+
+        match self.filter {
+            Filter::Bilinear => self.sample(uv),
+            Filter::Trilinear => self.trilinear(uv, (length(duvdx).max(length(duvdy)) * size).log2()),
+            Filter::Ewa => {
+                let (major, mut minor) = if length(duvdx) >= length(duvdy) { (duvdx, duvdy) } else { (duvdy, duvdx) };
+                let (major_length, minor_length) = (length(major), length(minor));
+
+                if major_length == 0.0 {
+                    return self.sample(uv);
+                }
+
+                if minor_length * MAX_ANISOTROPY < major_length {
+                    // widen the minor axis, keeping it perpendicular if degenerate
+                    let scale = major_length / MAX_ANISOTROPY;
+                    minor = if minor_length > 0.0 {
+                        (minor.0 * scale / minor_length, minor.1 * scale / minor_length)
+                    } else {
+                        (-major.1 * scale / major_length, major.0 * scale / major_length)
+                    };
+                }
+
+                let lod = (length(minor) * size).log2().max(0.0);
+                let level = lod.floor();
+                let t = lod - level;
+
+                if level as usize + 1 >= self.image.levels() {
+                    return self.ewa(self.image.level(self.image.levels() - 1), uv, major, minor);
+                }
+
+                self.ewa(self.image.level(level as usize), uv, major, minor) * (1.0 - t)
+                    + self.ewa(self.image.level(level as usize + 1), uv, major, minor) * t
+            }
+        }
+    }
+
+    fn trilinear(&self, uv: (f64, f64), lod: f64) -> Color {
+        let lod = lod.clamp(0.0, (self.image.levels() - 1) as f64);
+        let level = lod.floor() as usize;
+        let t = lod - level as f64;
+
+        if t == 0.0 {
+            return self.bilinear(self.image.level(level), uv);
+        }
+
+        self.bilinear(self.image.level(level), uv) * (1.0 - t) + self.bilinear(self.image.level(level + 1), uv) * t
+    }
+
+    /// Gaussian weighted average of the texels inside the ellipse spanned by
+    /// the two axes, at least one texel wide.
+    fn ewa(&self, image: &Image, uv: (f64, f64), axis0: (f64, f64), axis1: (f64, f64)) -> Color {
+        let (width, height) = (image.width as f64, image.height as f64);
+        let s = uv.0 * width - 0.5;
+        let t = (1.0 - uv.1) * height - 0.5;
+        let (ds0, dt0) = (axis0.0 * width, -axis0.1 * height);
+        let (ds1, dt1) = (axis1.0 * width, -axis1.1 * height);
+
+        // implicit ellipse A s^2 + B s t + C t^2 = 1
+        let mut a = dt0 * dt0 + dt1 * dt1 + 1.0;
+        let mut b = -2.0 * (ds0 * dt0 + ds1 * dt1);
+        let mut c = ds0 * ds0 + ds1 * ds1 + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        a *= inv_f;
+        b *= inv_f;
+        c *= inv_f;
+
+        let determinant = -b * b + 4.0 * a * c;
+        let s_extent = 2.0 * (determinant * c).sqrt() / determinant;
+        let t_extent = 2.0 * (determinant * a).sqrt() / determinant;
+
+        let mut sum = Color::new(0.0, 0.0, 0.0, false);
+        let mut weights = 0.0;
+
+        for it in (t - t_extent).ceil() as i64..=(t + t_extent).floor() as i64 {
+            let tt = it as f64 - t;
+
+            for is in (s - s_extent).ceil() as i64..=(s + s_extent).floor() as i64 {
+                let ss = is as f64 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+
+                if r2 < 1.0 {
+                    let weight = (-2.0 * r2).exp() - (-2.0_f64).exp();
+                    sum = sum + self.texel(image, is, it) * weight;
+                    weights += weight;
+                }
+            }
+        }
+
+        if weights > 0.0 { sum / weights } else { self.bilinear(image, uv) }
+    }
+}
+
+impl Texture {
+    pub fn image(image: Arc<Image>, wrap: WrapMode) -> Texture {
+        Texture::Image(ImageTexture::new(image, wrap))
+    }
+
+    /// `view` is the direction from the hit towards the viewer.
+    pub fn value(&self, hit_record: &HitRecord, view: Vec3) -> Color {
+        match self {
+            Texture::Constant(color) => *color,
+            Texture::Image(texture) => texture.sample_footprint(hit_record.uv, hit_record.duvdx, hit_record.duvdy),
+            Texture::Procedural(procedural) => procedural.value(hit_record),
+            Texture::Graph(node) => node.eval(hit_record, view)
+        }
+    }
+}
+
+/// Images loaded from disk, keyed by path and color space so that materials
+/// referring to the same file share a single copy.
+#[derive(Debug, Default)]
+pub struct TextureCache {
+    images: HashMap<(PathBuf, ColorSpace), Arc<Image>>
+}
+
+impl TextureCache {
+    pub fn new() -> TextureCache {
+        TextureCache { images: HashMap::new() }
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P, color_space: ColorSpace) -> io::Result<Arc<Image>> {
+        let key = (path.as_ref().to_path_buf(), color_space);
+
+        if let Some(image) = self.images.get(&key) {
+            return Ok(Arc::clone(image));
+        }
+
+        let image = Arc::new(Image::load(path, color_space)?);
+        self.images.insert(key, Arc::clone(&image));
+
+        Ok(image)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
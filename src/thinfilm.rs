@@ -0,0 +1,165 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use crate::vec3::Vec3;
+
+use Vec3 as Color;
+
+/// Wavelengths, in nanometers, integrated for each RGB channel together with
+/// their relative weights, a coarse gaussian fit of the sRGB primaries.
+const CHANNEL_SAMPLES_COUNT: usize = 8;
+const CHANNEL_CENTERS: [f64; 3] = [610.0, 550.0, 465.0];
+const CHANNEL_WIDTHS: [f64; 3] = [40.0, 35.0, 30.0];
+
+/// Thin transparent layer on top of a surface, such as an oil film, a soap
+/// bubble or an anti-reflective coating. `thickness` is given in nanometers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinFilm {
+    pub thickness: f64,
+    pub ior: f64
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    fn norm2(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Principal square root, with a non-negative imaginary part so that
+    /// evanescent waves decay.
+    fn sqrt(&self) -> Complex {
+        let r = self.norm2().sqrt();
+        let re = (0.5 * (r + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (r - self.re)).max(0.0).sqrt();
+
+        Complex::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    fn exp_i(phase: Complex) -> Complex {
+        // e^(i z) = e^(-im) (cos re + i sin re)
+        let scale = (-phase.im).exp();
+
+        Complex::new(scale * phase.re.cos(), scale * phase.re.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    fn div(self, other: Complex) -> Complex {
+        let d = other.norm2().max(1e-300);
+
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / d,
+            (self.im * other.re - self.re * other.im) / d
+        )
+    }
+}
+
+/// Fresnel amplitude coefficients `(r_s, r_p)` between two layers, given the
+/// indices of refraction and the cosines of the angles in each of them.
+fn amplitudes(n_i: Complex, cos_i: Complex, n_t: Complex, cos_t: Complex) -> (Complex, Complex) {
+    let r_s = (n_i * cos_i - n_t * cos_t) / (n_i * cos_i + n_t * cos_t);
+    let r_p = (n_t * cos_i - n_i * cos_t) / (n_t * cos_i + n_i * cos_t);
+
+    (r_s, r_p)
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> ThinFilm {
+        ThinFilm { thickness, ior }
+    }
+
+    /// Reflectance at a single `wavelength` (in nanometers) of the film lying
+    /// between an outer medium of index `eta_i` and a substrate of complex
+    /// index `eta_t + i k_t`, using the Airy summation of all internal
+    /// reflections.
+    pub fn reflectance_at(&self, cos_theta_i: f64, wavelength: f64, eta_i: f64, eta_t: f64, k_t: f64) -> f64 {
+        let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+        let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+
+        let n1 = Complex::new(eta_i, 0.0);
+        let n2 = Complex::new(self.ior, 0.0);
+        let n3 = Complex::new(eta_t, k_t);
+
+        // Snell's law with complex indices, cos_j = sqrt(1 - (n1 sin_1 / n_j)^2)
+        let cosine = |n: Complex| {
+            let sin = Complex::new(eta_i * sin2_theta_i.sqrt(), 0.0) / n;
+            (Complex::new(1.0, 0.0) - sin * sin).sqrt()
+        };
+
+        let cos1 = Complex::new(cos_theta_i, 0.0);
+        let cos2 = cosine(n2);
+        let cos3 = cosine(n3);
+
+        let (r12_s, r12_p) = amplitudes(n1, cos1, n2, cos2);
+        let (r23_s, r23_p) = amplitudes(n2, cos2, n3, cos3);
+
+        // round trip phase through the film
+        let phase = Complex::new(4.0 * std::f64::consts::PI * self.thickness / wavelength, 0.0) * n2 * cos2;
+        let shift = Complex::exp_i(phase);
+        let one = Complex::new(1.0, 0.0);
+
+        let r_s = (r12_s + r23_s * shift) / (one + r12_s * r23_s * shift);
+        let r_p = (r12_p + r23_p * shift) / (one + r12_p * r23_p * shift);
+
+        (0.5 * (r_s.norm2() + r_p.norm2())).clamp(0.0, 1.0)
+    }
+
+    /// RGB reflectance of the film over a substrate with per-channel complex
+    /// index `eta_t + i k_t`, integrating each channel over a band of
+    /// wavelengths so that thick films fade to their incoherent average
+    /// instead of aliasing.
+    pub fn reflectance(&self, cos_theta_i: f64, eta_i: f64, eta_t: Color, k_t: Color) -> Color {
+        let channel = |c: usize| {
+            let mut sum = 0.0;
+            let mut weights = 0.0;
+
+            for i in 0..CHANNEL_SAMPLES_COUNT {
+                // samples spread over +-2 standard deviations
+                let x = 4.0 * ((i as f64 + 0.5) / CHANNEL_SAMPLES_COUNT as f64) - 2.0;
+                let weight = (-0.5 * x * x).exp();
+                let wavelength = CHANNEL_CENTERS[c] + x * CHANNEL_WIDTHS[c];
+
+                sum += weight * self.reflectance_at(cos_theta_i, wavelength, eta_i, eta_t[c], k_t[c]);
+                weights += weight;
+            }
+
+            sum / weights
+        };
+
+        Color::new(channel(0), channel(1), channel(2), false)
+    }
+}
@@ -60,6 +60,37 @@ impl Vec3 {
     pub fn reflect(&self, normal: Vec3) -> Vec3 {
         *self - 2.0 * self.dot(normal) * normal
     }
+
+    /// Refracts the direction through a surface with the given normal, where
+    /// `eta` is the ratio of the incident to the transmitted index of
+    /// refraction. Returns `None` on total internal reflection.
+    pub fn refract(&self, normal: Vec3, eta: f64) -> Option<Vec3> {
+        let uv = self.normalized();
+        let cos_theta = (-uv).dot(normal).min(1.0);
+        let sin2_theta_t = eta * eta * (1.0 - cos_theta * cos_theta);
+
+        if sin2_theta_t > 1.0 {
+            return None;
+        }
+
+        let r_out_perp = eta * (uv + cos_theta * normal);
+        let r_out_parallel = -(1.0 - sin2_theta_t).sqrt() * normal;
+
+        Some(r_out_perp + r_out_parallel)
+    }
+
+    /// Builds two unit vectors that together with `self` (assumed normalized)
+    /// form a right-handed orthonormal basis.
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x, false),
+            Vec3::new(b, sign + self.y * self.y * a, -self.y, false)
+        )
+    }
 }
 
 impl Add for Vec3 {
@@ -253,9 +284,9 @@ impl Transform for Vec3 {
         let mut zp = self.x * matrix[2][0] + self.y * matrix[2][1] + self.z * matrix[2][2];
 
         if self.is_point {
-            xp = xp + matrix[0][3];
-            yp = yp + matrix[1][3];
-            zp = zp + matrix[2][3];
+            xp += matrix[0][3];
+            yp += matrix[1][3];
+            zp += matrix[2][3];
 
             let wp = self.x * matrix[3][0] + self.y * matrix[3][1] + self.z * matrix[3][2] + matrix[3][3];
 
@@ -0,0 +1,41 @@
+use raytracer::aabb::Aabb;
+use raytracer::ray::Ray;
+use raytracer::vec3::Vec3;
+use raytracer::transform::{translation_matrix, y_rotation_matrix};
+
+use Vec3 as Point3;
+
+#[test]
+fn test_aabb_union_and_empty() {
+    let a = Aabb::new(Point3::new(0.0, 0.0, 0.0, true), Point3::new(1.0, 1.0, 1.0, true));
+    let b = Aabb::new(Point3::new(-1.0, 0.5, 0.5, true), Point3::new(0.5, 1.5, 0.5, true));
+
+    assert!(Aabb::empty().is_empty());
+    assert_eq!(Aabb::empty().union(&a), a);
+
+    let union = a.union(&b);
+    assert_eq!(union.min, Point3::new(-1.0, 0.0, 0.0, true));
+    assert_eq!(union.max, Point3::new(1.0, 1.5, 1.0, true));
+    assert_eq!(union.longest_axis(), 0);
+}
+
+#[test]
+fn test_aabb_hit() {
+    let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0, true), Point3::new(1.0, 1.0, 1.0, true));
+    let ray = Ray::new(Point3::new(0.0, 0.0, 5.0, true), Vec3::new(0.0, 0.0, -1.0, false));
+
+    assert!(aabb.hit(&ray, 0.0, f64::INFINITY));
+    assert!(!aabb.hit(&ray, 0.0, 3.0));
+    assert!(!aabb.hit(&Ray::new(Point3::new(2.0, 0.0, 5.0, true), Vec3::new(0.0, 0.0, -1.0, false)), 0.0, f64::INFINITY));
+}
+
+#[test]
+fn test_aabb_transformed() {
+    let aabb = Aabb::new(Point3::new(0.0, 0.0, 0.0, true), Point3::new(1.0, 1.0, 1.0, true));
+    let moved = aabb.transformed(&translation_matrix(&Vec3::new(1.0, 2.0, 3.0, false)).mat);
+    assert_eq!(moved.min, Point3::new(1.0, 2.0, 3.0, true));
+
+    let rotated = aabb.transformed(&y_rotation_matrix(45.0).mat);
+    let diagonal = 2.0_f64.sqrt();
+    assert!((rotated.max.x() - rotated.min.x() - diagonal).abs() < 1e-9);
+}
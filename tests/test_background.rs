@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use raytracer::background::{Background, EnvironmentMap};
+use raytracer::image::Image;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+fn environment(rotation: f64) -> EnvironmentMap {
+    // dark map with a single bright pixel just above the horizon
+    let mut pixels = vec![Color::new(0.01, 0.01, 0.01, false); 16 * 8];
+    pixels[3 * 16 + 4] = Color::new(100.0, 100.0, 100.0, false);
+
+    EnvironmentMap::new(Arc::new(Image::new(16, 8, pixels)), rotation, 2.0)
+}
+
+#[test]
+fn test_color_and_gradient() {
+    let color = Color::new(0.1, 0.2, 0.3, false);
+    assert_eq!(Background::Color(color).value(Vec3::new(1.0, 0.0, 0.0, false)), color);
+
+    let gradient = Background::Gradient { bottom: Color::new(0.0, 0.0, 0.0, false), top: Color::new(1.0, 1.0, 1.0, false) };
+    assert!((gradient.value(Vec3::new(0.0, 2.0, 0.0, false)).x() - 1.0).abs() < 1e-12);
+    assert!((gradient.value(Vec3::new(1.0, 0.0, 0.0, false)).x() - 0.5).abs() < 1e-12);
+    assert!(!gradient.is_sampled());
+    assert_eq!(gradient.pdf(Vec3::new(0.0, 1.0, 0.0, false)), 0.0);
+}
+
+#[test]
+fn test_environment_samples_bright_pixel() {
+    let map = environment(0.0);
+    let (direction, pdf) = map.sample((0.5, 0.5));
+
+    // the pixel center at (4.5 / 16, 3.5 / 8)
+    let phi = 2.0 * std::f64::consts::PI * 4.5 / 16.0;
+    let theta = std::f64::consts::PI * 3.5 / 8.0;
+    let expected = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false);
+    assert!((direction - expected).length() < 0.2);
+    assert!((map.pdf(direction) - pdf).abs() < 1e-9 * pdf);
+    assert!((map.value(expected).x() - 200.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_environment_rotation() {
+    let map = environment(0.0);
+    let rotated = environment(90.0);
+    let direction = Vec3::new(0.3, 0.2, -0.9, false);
+    let turned = Vec3::new(-direction.z(), direction.y(), direction.x(), false);
+
+    assert!((map.value(direction) - rotated.value(turned)).length() < 1e-9);
+    assert!((map.pdf(direction) - rotated.pdf(turned)).abs() < 1e-9);
+}
+
+#[test]
+fn test_environment_pdf_integrates_to_one() {
+    let map = environment(30.0);
+    let (n_theta, n_phi) = (200, 400);
+    let mut total = 0.0;
+
+    for i in 0..n_theta {
+        for j in 0..n_phi {
+            let theta = std::f64::consts::PI * (i as f64 + 0.5) / n_theta as f64;
+            let phi = 2.0 * std::f64::consts::PI * (j as f64 + 0.5) / n_phi as f64;
+            let direction = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false);
+            let solid_angle = theta.sin() * (std::f64::consts::PI / n_theta as f64) * (2.0 * std::f64::consts::PI / n_phi as f64);
+            total += map.pdf(direction) * solid_angle;
+        }
+    }
+
+    assert!((total - 1.0).abs() < 0.01);
+}
+
+#[test]
+fn test_empty_environment_is_black() {
+    let map = EnvironmentMap::new(Arc::new(Image::new(0, 0, Vec::new())), 0.0, 1.0);
+    let black = Color::new(0.0, 0.0, 0.0, false);
+    assert_eq!(map.value(Vec3::new(0.0, 1.0, 0.0, false)), black);
+    assert_eq!(map.value(map.sample((0.3, 0.6)).0), black);
+}
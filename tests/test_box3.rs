@@ -1,5 +1,5 @@
 use raytracer::box3::Box3;
-use raytracer::ray::Ray;
+use raytracer::ray::{Ray, Differentials};
 use raytracer::vec3::Vec3;
 use raytracer::hit::Hit;
 use raytracer::material::Material;
@@ -32,6 +32,11 @@ fn test_normal_at() {
 
     let normal = box3.normal_at(Vec3::new(-1.0, 0.0, 0.5, true));
     assert_eq!(normal, Vec3::new(1.0, 0.0, 0.0, false).normalized());
+
+    // edges and corners get the normal of one of their faces
+    let normal = box3.normal_at(Vec3::new(1.0, 1.0, 0.5, true));
+    assert!(normal == Vec3::new(1.0, 0.0, 0.0, false) || normal == Vec3::new(0.0, 1.0, 0.0, false));
+    assert_eq!(box3.normal_at(Vec3::new(-1.0, 1.0, -1.0, true)).length(), 1.0);
 }
 
 #[test]
@@ -75,3 +80,46 @@ fn test_hit_miss() {
     let hit_record = box3.hit(&ray_miss, 0.0, f64::INFINITY);
     assert!(hit_record.is_none());
 }
+
+#[test]
+fn test_uv_per_face() {
+    let material = Material::new(Color::new(0.0, 0.0, 0.0, false), 0.0, 0.0);
+    let box3 = Box3::new(Vec3::new(0.0, 0.0, 0.0, true), Vec3::new(2.0, 4.0, 8.0, true), material, None);
+
+    let ray = Ray::new(Vec3::new(5.0, 1.0, 2.0, true), Vec3::new(-1.0, 0.0, 0.0, false));
+    let hit_record = box3.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(hit_record.uv, (0.25, 0.25));
+    assert_eq!(hit_record.dpdu, Vec3::new(0.0, 0.0, 8.0, false));
+    assert_eq!(hit_record.dpdv, Vec3::new(0.0, 4.0, 0.0, false));
+
+    let ray = Ray::new(Vec3::new(1.0, 9.0, 2.0, true), Vec3::new(0.0, -1.0, 0.0, false));
+    let hit_record = box3.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(hit_record.uv, (0.5, 0.25));
+    assert_eq!(hit_record.dpdu, Vec3::new(2.0, 0.0, 0.0, false));
+
+    let ray = Ray::new(Vec3::new(1.0, 1.0, -3.0, true), Vec3::new(0.0, 0.0, 1.0, false));
+    let hit_record = box3.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    assert_eq!(hit_record.uv, (0.5, 0.25));
+    assert_eq!(hit_record.geometric_normal, hit_record.normal);
+}
+
+#[test]
+fn test_compute_differentials() {
+    let material = Material::new(Color::new(0.0, 0.0, 0.0, false), 0.0, 0.0);
+    let box3 = Box3::new(Vec3::new(0.0, 0.0, 0.0, true), Vec3::new(2.0, 4.0, 8.0, true), material, None);
+
+    let direction = Vec3::new(0.0, 0.0, -1.0, false);
+    let ray = Ray::with_differentials(Vec3::new(1.0, 1.0, 10.0, true), direction, Some(Differentials {
+        rx_origin: Vec3::new(1.1, 1.0, 10.0, true),
+        rx_direction: direction,
+        ry_origin: Vec3::new(1.0, 1.2, 10.0, true),
+        ry_direction: direction
+    }));
+
+    let mut hit_record = box3.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    hit_record.compute_differentials(&ray);
+
+    assert!((hit_record.dpdx - Vec3::new(0.1, 0.0, 0.0, false)).length() < 1e-12);
+    assert!((hit_record.duvdx.0 - 0.05).abs() < 1e-12 && hit_record.duvdx.1.abs() < 1e-12);
+    assert!(hit_record.duvdy.0.abs() < 1e-12 && (hit_record.duvdy.1 - 0.05).abs() < 1e-12);
+}
@@ -0,0 +1,201 @@
+use raytracer::brdf::*;
+use raytracer::vec3::Vec3;
+use raytracer::frame::Frame;
+use raytracer::material::Material;
+
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-10;
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < EPSILON
+}
+
+#[test]
+fn test_fresnel_dielectric_normal_incidence() {
+    // ((n1 - n2) / (n1 + n2))^2 for air to glass
+    assert!(approx_eq(fresnel_dielectric(1.0, 1.0 / 1.5), 0.04));
+    assert!(approx_eq(fresnel_dielectric(1.0, 1.5), 0.04));
+}
+
+#[test]
+fn test_fresnel_dielectric_grazing() {
+    assert!(approx_eq(fresnel_dielectric(0.0, 1.0 / 1.5), 1.0));
+}
+
+#[test]
+fn test_fresnel_dielectric_total_internal_reflection() {
+    let critical_cos = (1.0 - 1.0 / (1.5_f64 * 1.5)).sqrt();
+    assert_eq!(fresnel_dielectric(critical_cos - 0.01, 1.5), 1.0);
+    assert!(fresnel_dielectric(critical_cos + 0.01, 1.5) < 1.0);
+}
+
+fn projected_area(alpha_x: f64, alpha_z: f64) -> f64 {
+    // integral of D(h) cos(theta_h) over the hemisphere
+    let theta_steps = 400;
+    let phi_steps = 400;
+    let d_theta = std::f64::consts::FRAC_PI_2 / theta_steps as f64;
+    let d_phi = 2.0 * std::f64::consts::PI / phi_steps as f64;
+    let mut sum = 0.0;
+
+    for i in 0..theta_steps {
+        let theta = (i as f64 + 0.5) * d_theta;
+
+        for j in 0..phi_steps {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let h = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false);
+            sum += d(alpha_x, alpha_z, h) * theta.cos() * theta.sin() * d_theta * d_phi;
+        }
+    }
+
+    sum
+}
+
+#[test]
+fn test_d_normalized() {
+    assert!((projected_area(0.5, 0.5) - 1.0).abs() < 1e-3);
+    assert!((projected_area(0.6, 0.15) - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_d_anisotropic() {
+    let along_x = Vec3::new(0.3, 1.0, 0.0, false).normalized();
+    let along_z = Vec3::new(0.0, 1.0, 0.3, false).normalized();
+    assert!(approx_eq(d(0.3, 0.3, along_x), d(0.3, 0.3, along_z)));
+    assert!(d(0.6, 0.1, along_x) > d(0.6, 0.1, along_z));
+}
+
+#[test]
+fn test_alpha_clamps_anisotropy() {
+    let white = Color::new(1.0, 1.0, 1.0, false);
+    let (alpha_x, alpha_z) = Material::anisotropic(white, 0.4, 1.0, 2.0, 0.0).alpha();
+    assert!(alpha_x.is_finite() && alpha_z.is_finite());
+    assert_eq!((alpha_x, alpha_z), Material::anisotropic(white, 0.4, 1.0, 1.0, 0.0).alpha());
+    assert_eq!(Material::anisotropic(white, 0.4, 1.0, -1.0, 0.0).alpha(), (0.4, 0.4));
+}
+
+#[test]
+fn test_g1_limits() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let below = Vec3::new(0.0, -1.0, 0.0, false);
+    assert!(approx_eq(g1(0.5, 0.2, up), 1.0));
+    assert_eq!(g1(0.5, 0.2, below), 0.0);
+}
+
+#[test]
+fn test_sample_ggx_vndf_upper_hemisphere() {
+    let ve = Vec3::new(0.6, 0.5, -0.2, false).normalized();
+
+    for _ in 0..1000 {
+        let m = sample_ggx_vndf(ve, 0.7, 0.1);
+        assert!(m.y() >= 0.0);
+        assert!((m.length() - 1.0).abs() < 1e-9);
+    }
+}
+
+fn integrate_hemisphere<F: Fn(Vec3) -> f64>(integrand: F) -> f64 {
+    let theta_steps = 200;
+    let phi_steps = 200;
+    let d_theta = std::f64::consts::FRAC_PI_2 / theta_steps as f64;
+    let d_phi = 2.0 * std::f64::consts::PI / phi_steps as f64;
+    let mut sum = 0.0;
+
+    for i in 0..theta_steps {
+        let theta = (i as f64 + 0.5) * d_theta;
+
+        for j in 0..phi_steps {
+            let phi = (j as f64 + 0.5) * d_phi;
+            let l = Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false);
+            sum += integrand(l) * theta.sin() * d_theta * d_phi;
+        }
+    }
+
+    sum
+}
+
+fn principled_materials() -> Vec<Material> {
+    let white = Color::new(1.0, 1.0, 1.0, false);
+
+    vec![
+        Material::new(white, 1.0, 0.0),
+        Material::new(white, 0.3, 1.0),
+        Material { sheen: 1.0, sheen_tint: 0.0, ..Material::new(white, 1.0, 0.0) },
+        Material { clearcoat: 1.0, clearcoat_roughness: 0.1, ..Material::new(white, 0.5, 0.0) },
+        Material { subsurface: 1.0, ..Material::new(white, 0.8, 0.0) },
+        Material { subsurface: 1.0, ..Material::new(white, 0.0, 0.0) },
+        Material::anisotropic(white, 0.4, 1.0, 0.8, 30.0),
+    ]
+}
+
+#[test]
+fn test_brdf_energy_conservation() {
+    let frame = Frame::from_normal(Vec3::new(0.0, 1.0, 0.0, false));
+
+    for material in principled_materials() {
+        for cos_theta_v in [1.0_f64, 0.7, 0.3, 0.1] {
+            let v = Vec3::new((1.0 - cos_theta_v * cos_theta_v).sqrt(), cos_theta_v, 0.0, false);
+            let albedo = integrate_hemisphere(|l| brdf(&material, &frame, v, l).y() * l.y());
+            assert!(albedo <= 1.01, "albedo {} for {:?}", albedo, material);
+        }
+    }
+}
+
+#[test]
+fn test_pdf_normalized() {
+    let frame = Frame::from_normal(Vec3::new(0.0, 1.0, 0.0, false));
+    let v = Vec3::new(0.0, 1.0, 0.0, false);
+
+    // specular samples reflected below the horizon are discarded
+    for material in principled_materials() {
+        let total = integrate_hemisphere(|l| pdf(&material, &frame, v, l));
+        assert!(total > 0.5 && total < 1.01, "pdf integrates to {} for {:?}", total, material);
+    }
+
+    // smooth lobes seen head-on lose almost nothing below the horizon
+    let white = Color::new(1.0, 1.0, 1.0, false);
+    let above = [
+        Material::new(white, 0.05, 0.0),
+        Material::new(white, 0.05, 1.0),
+        Material { clearcoat: 1.0, clearcoat_roughness: 0.05, ..Material::new(white, 0.05, 0.0) },
+        Material { sheen: 1.0, subsurface: 1.0, ..Material::new(white, 0.05, 0.0) },
+        Material::anisotropic(white, 0.05, 1.0, 0.5, 30.0),
+    ];
+
+    for material in above {
+        let total = integrate_hemisphere(|l| pdf(&material, &frame, v, l));
+        assert!((total - 1.0).abs() < 0.005, "pdf integrates to {} for {:?}", total, material);
+    }
+}
+
+#[test]
+fn test_sample_brdf_above_surface() {
+    let frame = Frame::from_normal(Vec3::new(0.3, 1.0, -0.2, false).normalized());
+    let v = Vec3::new(0.5, 0.8, 0.1, false);
+
+    for material in principled_materials() {
+        for _ in 0..200 {
+            if let Some(l) = sample_brdf(&material, &frame, v) {
+                assert!(l.dot(frame.normal) > 0.0);
+                assert!(pdf(&material, &frame, v, l) > 0.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sample_brdf_lobe() {
+    let frame = Frame::from_normal(Vec3::new(0.0, 1.0, 0.0, false));
+    let v = Vec3::new(0.5, 0.8, 0.1, false);
+    let metal = Material::new(Color::new(0.9, 0.9, 0.9, false), 0.0, 1.0);
+    let plastic = Material::new(Color::new(0.9, 0.2, 0.2, false), 0.8, 0.0);
+
+    // a smooth metal has no diffuse lobe
+    for _ in 0..100 {
+        if let Some((_, lobe)) = sample_brdf_lobe(&metal, &frame, v) {
+            assert_eq!(lobe, Lobe::Specular);
+        }
+    }
+
+    let lobes: Vec<Lobe> = (0..200).filter_map(|_| sample_brdf_lobe(&plastic, &frame, v)).map(|(_, lobe)| lobe).collect();
+    assert!(lobes.contains(&Lobe::Diffuse) && lobes.contains(&Lobe::Specular));
+}
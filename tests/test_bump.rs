@@ -0,0 +1,92 @@
+use raytracer::bump::*;
+use raytracer::texture::Texture;
+use raytracer::procedural::{Procedural, Pattern, Space};
+use raytracer::material::Material;
+use raytracer::hit::HitRecord;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-6;
+
+fn hit_record(material: &Material) -> HitRecord<'_> {
+    HitRecord {
+        t_min: 1.0,
+        point: Point3::new(0.0, 0.0, 0.0, true),
+        point_error: Vec3::new(0.0, 0.0, 0.0, false),
+        object_point: Point3::new(0.0, 0.0, 0.0, true),
+        normal: Vec3::new(0.0, 1.0, 0.0, false),
+        geometric_normal: Vec3::new(0.0, 1.0, 0.0, false),
+        uv: (0.5, 0.5),
+        dpdu: Vec3::new(1.0, 0.0, 0.0, false),
+        dpdv: Vec3::new(0.0, 0.0, -1.0, false),
+        dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+        dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+        duvdx: (0.0, 0.0),
+        duvdy: (0.0, 0.0),
+        front_face: true,
+        material,
+        name: ""
+    }
+}
+
+fn up() -> Vec3 {
+    Vec3::new(0.0, 1.0, 0.0, false)
+}
+
+fn approx_eq(a: Vec3, b: Vec3) -> bool {
+    (a - b).length() < EPSILON
+}
+
+#[test]
+fn test_flat_normal_map() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material);
+    let flat = Texture::Constant(Color::new(0.5, 0.5, 1.0, false));
+
+    assert!(approx_eq(normal_map(&record, &flat, up()), record.normal));
+}
+
+#[test]
+fn test_normal_map_follows_uv_axes() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material);
+
+    let along_u = Texture::Constant(Color::new(1.0, 0.5, 0.5, false));
+    assert!(approx_eq(normal_map(&record, &along_u, up()), record.dpdu));
+
+    let along_v = Texture::Constant(Color::new(0.5, 1.0, 0.5, false));
+    assert!(approx_eq(normal_map(&record, &along_v, up()), record.dpdv));
+}
+
+#[test]
+fn test_bump_slope() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material);
+
+    // height rising with u at a slope of 0.5 tilts the normal towards -u
+    let ramp = Texture::Procedural(Procedural { space: Space::Uv, ..Procedural::new(Pattern::Gradient, (Color::new(0.0, 0.0, 0.0, false), Color::new(1.0, 1.0, 1.0, false))) });
+    let bumped = bump_map(&record, &ramp, 0.5, up());
+    assert!(approx_eq(bumped, Vec3::new(-0.5, 1.0, 0.0, false).normalized()));
+
+    let constant = Texture::Constant(Color::new(0.3, 0.3, 0.3, false));
+    assert!(approx_eq(bump_map(&record, &constant, 4.0, up()), record.normal));
+}
+
+#[test]
+fn test_shading_normal_faces_viewer() {
+    let material = Material {
+        normal_map: Some(Texture::Constant(Color::new(1.0, 0.5, 0.5, false))),
+        ..Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0)
+    };
+    let record = hit_record(&material);
+    let view = Vec3::new(-1.0, 1.0, 0.0, false).normalized();
+
+    let normal = shading_normal(&material, &record, view);
+    assert!((normal.length() - 1.0).abs() < EPSILON);
+    assert!(normal.dot(view) > 0.0);
+
+    let plain = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    assert_eq!(shading_normal(&plain, &hit_record(&plain), view), record.normal);
+}
@@ -1,6 +1,5 @@
 use raytracer::camera::Camera;
 use raytracer::vec3::Vec3;
-use raytracer::ray::Ray;
 
 #[test]
 fn test_camera_get_ray() {
@@ -15,4 +14,19 @@ fn test_camera_get_ray() {
     let ray = camera.get_ray(0.5, 0.5);
     let expected_direction = Vec3::new(0.0, 0.0, -1.0, true);
     assert_eq!(ray.direction().normalized(), expected_direction);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_camera_get_ray_differential() {
+    let look_from = Vec3::new(0.0, 0.0, 0.0, true);
+    let look_at = Vec3::new(0.0, 0.0, -1.0, true);
+    let vup = Vec3::new(0.0, 1.0, 0.0, false);
+    let camera = Camera::new(look_from, look_at, vup, 90.0, 1.0);
+
+    let ray = camera.get_ray_differential(0.5, 0.5, 0.01, 0.02);
+    let differentials = ray.differentials().unwrap();
+    assert_eq!(ray.direction(), camera.get_ray(0.5, 0.5).direction());
+    assert_eq!(differentials.rx_direction, camera.get_ray(0.51, 0.5).direction());
+    assert_eq!(differentials.ry_direction, camera.get_ray(0.5, 0.52).direction());
+    assert!(camera.get_ray(0.5, 0.5).differentials().is_none());
+}
@@ -0,0 +1,44 @@
+use raytracer::distribution::{Distribution1D, Distribution2D};
+
+#[test]
+fn test_distribution_1d() {
+    let distribution = Distribution1D::new(&[1.0, 0.0, 3.0]);
+    assert!((distribution.integral - 4.0 / 3.0).abs() < 1e-12);
+
+    // a quarter of the mass lies in the first segment
+    let (x, pdf, index) = distribution.sample(0.125);
+    assert_eq!(index, 0);
+    assert!((x - 1.0 / 6.0).abs() < 1e-12);
+    assert!((pdf - 0.75).abs() < 1e-12);
+
+    // the empty segment is never picked
+    let (x, pdf, index) = distribution.sample(0.25);
+    assert_eq!(index, 2);
+    assert!((x - 2.0 / 3.0).abs() < 1e-12);
+    assert!((pdf - 2.25).abs() < 1e-12);
+    assert_eq!(distribution.pdf(0.5), 0.0);
+}
+
+#[test]
+fn test_distribution_1d_all_zero_is_uniform() {
+    let distribution = Distribution1D::new(&[0.0, 0.0]);
+    let (x, pdf, index) = distribution.sample(0.75);
+    assert_eq!(index, 1);
+    assert!((x - 0.75).abs() < 1e-12);
+    assert_eq!(pdf, 1.0);
+}
+
+#[test]
+fn test_distribution_2d() {
+    let distribution = Distribution2D::new(&[1.0, 1.0, 0.0, 6.0], 2, 2);
+
+    for &u in &[(0.1, 0.1), (0.9, 0.2), (0.3, 0.6), (0.7, 0.95)] {
+        let (point, pdf) = distribution.sample(u);
+        assert!((distribution.pdf(point) - pdf).abs() < 1e-12);
+        assert!(pdf > 0.0);
+    }
+
+    // densities integrate to one
+    let total: f64 = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)].iter().map(|&p| distribution.pdf(p) * 0.25).sum();
+    assert!((total - 1.0).abs() < 1e-12);
+}
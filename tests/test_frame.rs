@@ -0,0 +1,42 @@
+use raytracer::frame::Frame;
+use raytracer::vec3::Vec3;
+
+const EPSILON: f64 = 1e-10;
+
+fn approx_eq(a: Vec3, b: Vec3) -> bool {
+    (a.x() - b.x()).abs() < EPSILON &&
+    (a.y() - b.y()).abs() < EPSILON &&
+    (a.z() - b.z()).abs() < EPSILON
+}
+
+#[test]
+fn test_frame_new_orthogonalizes_tangent() {
+    let normal = Vec3::new(0.0, 1.0, 0.0, false);
+    let frame = Frame::new(normal, Vec3::new(1.0, 1.0, 0.0, false));
+    assert!(approx_eq(frame.tangent, Vec3::new(1.0, 0.0, 0.0, false)));
+    assert!(approx_eq(frame.bitangent, Vec3::new(0.0, 0.0, 1.0, false)));
+}
+
+#[test]
+fn test_frame_degenerate_tangent() {
+    let normal = Vec3::new(0.0, 0.0, 1.0, false);
+    let frame = Frame::new(normal, normal);
+    assert!(frame.tangent.dot(normal).abs() < EPSILON);
+    assert!((frame.tangent.length() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn test_frame_round_trip() {
+    let frame = Frame::from_normal(Vec3::new(1.0, 2.0, 3.0, false).normalized());
+    let v = Vec3::new(-0.3, 0.8, 0.5, false);
+    assert!(approx_eq(frame.to_world(frame.to_local(v)), v));
+    assert!(approx_eq(frame.to_local(frame.normal), Vec3::new(0.0, 1.0, 0.0, false)));
+}
+
+#[test]
+fn test_frame_rotated() {
+    let frame = Frame::new(Vec3::new(0.0, 1.0, 0.0, false), Vec3::new(1.0, 0.0, 0.0, false));
+    let rotated = frame.rotated(90.0);
+    assert!(approx_eq(rotated.tangent, frame.bitangent));
+    assert!(approx_eq(rotated.normal, frame.normal));
+}
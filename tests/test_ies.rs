@@ -0,0 +1,94 @@
+use raytracer::ies::IesProfile;
+use raytracer::vec3::Vec3;
+
+const SYMMETRIC: &str = "IESNA:LM-63-2002
+[TEST] rotationally symmetric downlight
+[MANUFAC] none
+TILT=NONE
+1 1000 2.0 5 1 1 2 0 0 0
+1.0 1.0 100
+0 22.5 45 67.5 90
+0
+100 90 50 10 0
+";
+
+const QUADRANT: &str = "TILT=NONE
+1 -1 1 3 3 1 2 0 0 0
+0.5 1 50
+0 45 90
+0 45 90
+100 100 100
+100 60 20
+100 20 0
+";
+
+#[test]
+fn test_parse_symmetric() {
+    let profile = IesProfile::parse(SYMMETRIC).unwrap();
+    assert_eq!(profile.vertical_angles, vec![0.0, 22.5, 45.0, 67.5, 90.0]);
+    assert_eq!(profile.candela, vec![vec![200.0, 180.0, 100.0, 20.0, 0.0]]);
+    assert_eq!(profile.max_candela(), 200.0);
+
+    // interpolated between vertical samples, independent of the horizontal angle
+    assert!((profile.candela(33.75, 0.0) - 140.0).abs() < 1e-9);
+    assert!((profile.candela(33.75, 217.0) - 140.0).abs() < 1e-9);
+    // nothing is emitted above the measured range
+    assert_eq!(profile.candela(120.0, 0.0), 0.0);
+}
+
+#[test]
+fn test_quadrant_symmetry() {
+    let profile = IesProfile::parse(QUADRANT).unwrap();
+
+    // the ballast factor scales the whole distribution
+    assert!((profile.candela(45.0, 45.0) - 30.0).abs() < 1e-9);
+    assert!((profile.candela(90.0, 22.5) - 30.0).abs() < 1e-9);
+
+    // the other quadrants mirror the measured one
+    for horizontal in [135.0, 225.0, 315.0, -45.0] {
+        assert!((profile.candela(45.0, horizontal) - 30.0).abs() < 1e-9);
+    }
+
+    assert!((profile.candela(67.5, 180.0) - profile.candela(67.5, 0.0)).abs() < 1e-9);
+    assert!((profile.candela(67.5, 270.0) - profile.candela(67.5, 90.0)).abs() < 1e-9);
+}
+
+#[test]
+fn test_intensity_frame() {
+    let profile = IesProfile::parse(QUADRANT).unwrap();
+
+    assert!((profile.intensity(Vec3::new(0.0, -2.0, 0.0, false)) - 50.0).abs() < 1e-9);
+    // horizontal angle 0 along +x, 90 along -z
+    assert!((profile.intensity(Vec3::new(1.0, 0.0, 0.0, false)) - 50.0).abs() < 1e-9);
+    assert!(profile.intensity(Vec3::new(0.0, 0.0, -1.0, false)).abs() < 1e-9);
+    assert_eq!(profile.intensity(Vec3::new(0.0, 1.0, 0.0, false)), 0.0);
+}
+
+#[test]
+fn test_tilt_include_and_errors() {
+    let source = "TILT=INCLUDE\n1\n2\n0 90\n1 1\n1 1000 1 2 1 1 2 0 0 0\n1 1 10\n0 90\n0\n10 5\n";
+    let profile = IesProfile::parse(source).unwrap();
+    assert_eq!(profile.candela, vec![vec![10.0, 5.0]]);
+
+    assert!(IesProfile::parse("no tilt line").is_err());
+    assert!(IesProfile::parse("TILT=NONE\n1 1000 1 2 1 1 2 0 0 0\n1 1 10\n0 90\n0\n10\n").is_err());
+    assert!(IesProfile::parse("TILT=NONE\n1 1000 1 2 1 3 2 0 0 0\n1 1 10\n0 90\n0\n10 5\n").is_err());
+    assert!(IesProfile::parse("TILT=NONE\n1 1000 1 2 1 1 2 0 0 0\n1 1 10\n90 0\n0\n10 5\n").is_err());
+
+    // counts that are garbage rather than truncated
+    assert!(IesProfile::parse("TILT=INCLUDE\n1\n1e30\n0 90\n").is_err());
+    assert!(IesProfile::parse("TILT=INCLUDE\n1\n-2\n0 90\n").is_err());
+    assert!(IesProfile::parse("TILT=NONE\n1 1000 1 2.5 1 1 2 0 0 0\n1 1 10\n0 90\n0\n10 5\n").is_err());
+}
+
+#[test]
+fn test_half_symmetry_from_90_to_270() {
+    let source = "TILT=NONE\n1 -1 1 2 3 1 2 0 0 0\n1 1 10\n0 90\n90 180 270\n10 10\n20 20\n30 30\n";
+    let profile = IesProfile::parse(source).unwrap();
+
+    // the other half mirrors about the plane through 90 and 270
+    assert!((profile.candela(45.0, 180.0) - 20.0).abs() < 1e-9);
+    assert!((profile.candela(45.0, 0.0) - 20.0).abs() < 1e-9);
+    assert!((profile.candela(45.0, 45.0) - profile.candela(45.0, 135.0)).abs() < 1e-9);
+    assert!((profile.candela(45.0, 315.0) - profile.candela(45.0, 225.0)).abs() < 1e-9);
+}
@@ -0,0 +1,88 @@
+use raytracer::image::*;
+
+const EPSILON: f64 = 1e-6;
+
+#[test]
+fn test_ascii_ppm() {
+    let bytes = b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n";
+    let image = Image::from_bytes(bytes, ColorSpace::Linear).unwrap();
+    assert_eq!((image.width, image.height), (2, 1));
+    assert!((image.pixel(0, 0).x() - 1.0).abs() < EPSILON);
+    assert!((image.pixel(1, 0).z() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn test_binary_ppm_srgb() {
+    let mut bytes = b"P6 1 1 255\n".to_vec();
+    bytes.extend_from_slice(&[128, 0, 255]);
+    let image = Image::from_bytes(&bytes, ColorSpace::Srgb).unwrap();
+    assert!((image.pixel(0, 0).x() - srgb_to_linear(128.0 / 255.0)).abs() < EPSILON);
+    assert!((image.pixel(0, 0).x() - 0.2158605).abs() < EPSILON);
+    assert!((image.pixel(0, 0).z() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn test_pfm_rows_bottom_up() {
+    let mut bytes = b"Pf\n1 2\n-1.0\n".to_vec();
+    bytes.extend_from_slice(&0.25_f32.to_le_bytes());
+    bytes.extend_from_slice(&4.0_f32.to_le_bytes());
+    let image = Image::from_bytes(&bytes, ColorSpace::Srgb).unwrap();
+    assert!((image.pixel(0, 0).y() - 4.0).abs() < EPSILON);
+    assert!((image.pixel(0, 1).y() - 0.25).abs() < EPSILON);
+}
+
+#[test]
+fn test_png() {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 255, 0, 51, 0, 0]).unwrap();
+    }
+
+    let image = Image::from_bytes(&bytes, ColorSpace::Linear).unwrap();
+    assert_eq!((image.width, image.height), (2, 1));
+    assert!((image.pixel(0, 0).x() - 1.0).abs() < EPSILON);
+    assert!((image.pixel(1, 0).y() - 0.2).abs() < EPSILON);
+}
+
+#[test]
+fn test_invalid_image() {
+    assert!(Image::from_bytes(b"GIF89a", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"P6 4 4 255\n\x00", ColorSpace::Linear).is_err());
+
+    // empty images and sizes overflowing the pixel count
+    assert!(Image::from_bytes(b"P3 0 1 255\n", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"Pf 1 0 -1.0\n", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"P6 4294967296 4294967296 255\n\x00", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"PF 18446744073709551615 2 -1.0\n\x00", ColorSpace::Linear).is_err());
+}
+
+#[test]
+fn test_hdr_flat_and_run_length() {
+    // flat scanline: mantissa 128 with exponent 129 is 1.0
+    let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+    bytes.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+    let image = Image::from_bytes(&bytes, ColorSpace::Srgb).unwrap();
+    assert_eq!((image.width, image.height), (2, 1));
+    assert!((image.pixel(0, 0).x() - 1.0).abs() < EPSILON);
+    assert!((image.pixel(0, 0).y() - 0.5).abs() < EPSILON);
+    assert!(image.pixel(1, 0).x().abs() < EPSILON);
+
+    // run-length scanline of 8 pixels, each channel a single run
+    let mut bytes = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+    bytes.extend_from_slice(&[2, 2, 0, 8, 136, 128, 136, 0, 136, 32, 136, 130]);
+    let image = Image::from_bytes(&bytes, ColorSpace::Linear).unwrap();
+    assert!((image.pixel(7, 0).x() - 2.0).abs() < EPSILON);
+    assert!(image.pixel(3, 0).y().abs() < EPSILON);
+    assert!((image.pixel(0, 0).z() - 0.5).abs() < EPSILON);
+
+    assert!(Image::from_bytes(b"#?RADIANCE\n\n+Y 1 +X 8\n", ColorSpace::Linear).is_err());
+
+    // empty, overflowing or oversized headers fail before allocating
+    assert!(Image::from_bytes(b"#?RADIANCE\n\n-Y 1 +X 0\n\x01\x02\x03\x04", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"#?RADIANCE\n\n-Y 4294967296 +X 4294967296\n", ColorSpace::Linear).is_err());
+    assert!(Image::from_bytes(b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x00", ColorSpace::Linear).is_err());
+}
@@ -1,4 +1,10 @@
-use raytracer::light::Light;
+use std::sync::Arc;
+
+use raytracer::light::{Light, Linking, Projector};
+use raytracer::ies::IesProfile;
+use raytracer::image::Image;
+use raytracer::texture::{ImageTexture, WrapMode};
+use raytracer::transform::{translation_matrix, x_rotation_matrix};
 use raytracer::vec3::Vec3;
 
 #[test]
@@ -13,4 +19,109 @@ fn test_light_sample() {
         let distance = (sample - position).length();
         assert!(distance <= radius);
     }
-}
\ No newline at end of file
+}
+
+const DOWNLIGHT: &str = "TILT=NONE
+1 -1 1 3 1 1 2 0 0 0
+1 1 10
+0 45 90
+0
+100 50 0
+";
+
+#[test]
+fn test_light_profile_falloff() {
+    let mut light = Light::new(Vec3::new(1.0, 1.0, 1.0, false), Vec3::new(0.0, 4.0, 0.0, true), 0.0);
+    light.profile = Some(IesProfile::parse(DOWNLIGHT).unwrap());
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+
+    let below = light.irradiance(light.position, Vec3::new(0.0, 0.0, 0.0, true), up);
+    let closer = light.irradiance(light.position, Vec3::new(0.0, 2.0, 0.0, true), up);
+    assert!((below.x() - 100.0 / 16.0).abs() < 1e-9);
+    assert!((closer.x() - 4.0 * below.x()).abs() < 1e-9);
+
+    // 45 degrees off the nadir, at twice the squared distance and a 45 degree incidence
+    let off_axis = light.irradiance(light.position, Vec3::new(4.0, 0.0, 0.0, true), up);
+    assert!((off_axis.x() - 50.0 / 32.0 * 0.5_f64.sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn test_uniform_light_ignores_distance() {
+    let light = Light::new(Vec3::new(1.0, 1.0, 1.0, false), Vec3::new(0.0, 4.0, 0.0, true), 0.0);
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+
+    let below = light.irradiance(light.position, Vec3::new(0.0, 0.0, 0.0, true), up);
+    let closer = light.irradiance(light.position, Vec3::new(0.0, 2.0, 0.0, true), up);
+    assert!((below.x() - 1.0).abs() < 1e-9);
+    assert!((closer.x() - 1.0).abs() < 1e-9);
+
+    let off_axis = light.irradiance(light.position, Vec3::new(4.0, 0.0, 0.0, true), up);
+    assert!((off_axis.x() - 0.5_f64.sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn test_light_profile_orientation() {
+    let mut light = Light::new(Vec3::new(1.0, 0.5, 0.0, false), Vec3::new(0.0, 0.0, 0.0, true), 0.0);
+    light.profile = Some(IesProfile::parse(DOWNLIGHT).unwrap());
+    light.transform_matrix = Some(x_rotation_matrix(180.0));
+
+    // flipped to shine upwards, tinted by the light's color
+    let up = light.emission(Vec3::new(0.0, 1.0, 0.0, false));
+    assert!((up.x() - 100.0).abs() < 1e-9 && (up.y() - 50.0).abs() < 1e-9);
+    assert!(light.emission(Vec3::new(0.0, -1.0, 0.0, false)).x().abs() < 1e-9);
+
+    // directions given as point differences are not translated
+    light.transform_matrix = Some(translation_matrix(&Vec3::new(0.0, 5.0, 0.0, false)));
+    assert!((light.emission(Vec3::new(0.0, -1.0, 0.0, true)).x() - 100.0).abs() < 1e-9);
+}
+
+/// Slide with a red left half and a blue right half.
+fn split_projector(vfov: f64, aspect_ratio: f64) -> Projector {
+    let image = Image::new(2, 1, vec![Vec3::new(1.0, 0.0, 0.0, false), Vec3::new(0.0, 0.0, 1.0, false)]);
+
+    Projector::new(vfov, aspect_ratio, ImageTexture::new(Arc::new(image), WrapMode::Clamp))
+}
+
+#[test]
+fn test_projector_frustum() {
+    let projector = split_projector(90.0, 2.0);
+
+    let left = projector.filter(Vec3::new(-1.0, 0.0, -1.0, false));
+    let right = projector.filter(Vec3::new(1.0, 0.0, -1.0, false));
+    assert!(left.x() > 0.99 && left.z() < 0.01);
+    assert!(right.z() > 0.99 && right.x() < 0.01);
+
+    // outside the frustum, and behind the projector
+    assert_eq!(projector.filter(Vec3::new(2.5, 0.0, -1.0, false)).x(), 0.0);
+    assert_eq!(projector.filter(Vec3::new(0.0, 1.5, -1.0, false)).z(), 0.0);
+    assert_eq!(projector.filter(Vec3::new(-1.0, 0.0, 1.0, false)).x(), 0.0);
+}
+
+#[test]
+fn test_projector_light() {
+    let mut light = Light::new(Vec3::new(2.0, 2.0, 2.0, false), Vec3::new(0.0, 4.0, 0.0, true), 0.0);
+    light.projector = Some(split_projector(60.0, 1.0));
+    light.transform_matrix = Some(x_rotation_matrix(-90.0));
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+
+    // aimed down, with the slide's left half towards -x
+    let left = light.irradiance(light.position, Vec3::new(-2.0, 0.0, 0.0, true), up);
+    let right = light.irradiance(light.position, Vec3::new(2.0, 0.0, 0.0, true), up);
+    let cos_theta = 4.0 / 20.0_f64.sqrt();
+    assert!((left.x() - 2.0 * cos_theta / 20.0).abs() < 1e-9 && left.z().abs() < 1e-9);
+    assert!((right.z() - 2.0 * cos_theta / 20.0).abs() < 1e-9 && right.x().abs() < 1e-9);
+
+    // outside the cone of light
+    assert_eq!(light.irradiance(light.position, Vec3::new(5.0, 0.0, 0.0, true), up).z(), 0.0);
+}
+
+#[test]
+fn test_linking() {
+    let hero = vec!["hero".to_string()];
+
+    assert!(Linking::All.includes("hero") && Linking::All.includes(""));
+    assert!(Linking::Include(hero.clone()).includes("hero"));
+    assert!(!Linking::Include(hero.clone()).includes("floor"));
+    assert!(!Linking::Exclude(hero.clone()).includes("hero"));
+    assert!(Linking::Exclude(hero).includes("floor"));
+}
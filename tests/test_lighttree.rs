@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use raytracer::light::{Light, Linking, Projector};
+use raytracer::lighttree::{LightBounds, LightTree};
+use raytracer::image::Image;
+use raytracer::texture::{ImageTexture, WrapMode};
+use raytracer::transform::x_rotation_matrix;
+use raytracer::vec3::Vec3;
+
+fn light(power: f64, x: f64, y: f64) -> Light {
+    Light::new(Vec3::new(power, power, power, false), Vec3::new(x, y, 0.0, true), 0.1)
+}
+
+#[test]
+fn test_light_tree_single_light() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let tree = LightTree::new(vec![light(1.0, 0.0, 5.0)]);
+
+    let (picked, pmf) = tree.sample(Vec3::new(0.0, 0.0, 0.0, true), up, 0.7).unwrap();
+    assert_eq!(picked.position, Vec3::new(0.0, 5.0, 0.0, true));
+    assert_eq!(pmf, 1.0);
+
+    // lights below the surface are never picked
+    assert!(tree.sample(Vec3::new(0.0, 10.0, 0.0, true), up, 0.7).is_none());
+    assert!(LightTree::new(Vec::new()).sample(Vec3::new(0.0, 0.0, 0.0, true), up, 0.7).is_none());
+}
+
+#[test]
+fn test_light_tree_proportional_to_power() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let tree = LightTree::new(vec![light(1.0, -5.0, 5.0), light(3.0, 5.0, 5.0)]);
+    let point = Vec3::new(0.0, 0.0, 0.0, true);
+
+    let (dim, dim_pmf) = tree.sample(point, up, 0.1).unwrap();
+    let (bright, bright_pmf) = tree.sample(point, up, 0.9).unwrap();
+    assert_eq!(dim.color.x(), 1.0);
+    assert_eq!(bright.color.x(), 3.0);
+    assert!((dim_pmf - 0.25).abs() < 1e-9 && (bright_pmf - 0.75).abs() < 1e-9);
+}
+
+#[test]
+fn test_light_tree_many_lights() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let lights = (0..100).map(|i| light(1.0 + (i % 7) as f64, (i % 10) as f64 * 3.0, 4.0 + (i / 10) as f64)).collect();
+    let tree = LightTree::new(lights);
+    let point = Vec3::new(10.0, 0.0, 0.0, true);
+    assert_eq!(tree.len(), 100);
+
+    // the probabilities of all lights add up to one
+    let mut probabilities = vec![0.0; tree.len()];
+    let samples = 20000;
+
+    for i in 0..samples {
+        let (picked, pmf) = tree.sample(point, up, (i as f64 + 0.5) / samples as f64).unwrap();
+        let index = tree.lights().iter().position(|light| std::ptr::eq(light, picked)).unwrap();
+        probabilities[index] = pmf;
+    }
+
+    assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_light_bounds_projector_cone() {
+    let image = Image::new(1, 1, vec![Vec3::new(1.0, 1.0, 1.0, false)]);
+    let mut projector = Light::new(Vec3::new(1.0, 1.0, 1.0, false), Vec3::new(0.0, 5.0, 0.0, true), 0.0);
+    projector.projector = Some(Projector::new(30.0, 1.0, ImageTexture::new(Arc::new(image), WrapMode::Clamp)));
+    projector.transform_matrix = Some(x_rotation_matrix(-90.0));
+    let bounds = LightBounds::from_light(&projector);
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+
+    // aimed down, reaching below but not far to the side
+    assert!((bounds.axis.y() + 1.0).abs() < 1e-9);
+    assert!(bounds.importance(Vec3::new(0.0, 0.0, 0.0, true), up) > 0.0);
+    assert_eq!(bounds.importance(Vec3::new(20.0, 0.0, 0.0, true), up), 0.0);
+
+    // a uniform light has no preferred direction
+    let uniform = LightBounds::from_light(&light(1.0, 0.0, 5.0));
+    let merged = bounds.union(&uniform);
+    assert_eq!(merged.cos_theta_o, -1.0);
+    assert_eq!(merged.power, 2.0);
+}
+
+#[test]
+fn test_light_tree_linking() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let point = Vec3::new(10.0, 0.0, 0.0, true);
+    let mut lights: Vec<Light> = (0..10).map(|i| light(1.0, i as f64 * 2.0, 5.0)).collect();
+
+    for light in &mut lights[..9] {
+        light.light_linking = Linking::Exclude(vec!["floor".to_string()]);
+    }
+
+    lights[9].light_linking = Linking::Include(vec!["floor".to_string()]);
+    let tree = LightTree::new(lights);
+
+    // unlinked lights are never picked, so no sample is wasted on them
+    for u in [0.05, 0.5, 0.95] {
+        let (picked, pmf) = tree.sample_linked("floor", point, up, u).unwrap();
+        assert!(std::ptr::eq(picked, &tree.lights()[9]));
+        assert_eq!(pmf, 1.0);
+    }
+
+    // every other receiver sees the nine remaining lights
+    let samples = 1000;
+    let mut probabilities = vec![0.0; tree.len()];
+
+    for i in 0..samples {
+        let (picked, pmf) = tree.sample_linked("wall", point, up, (i as f64 + 0.5) / samples as f64).unwrap();
+        let index = tree.lights().iter().position(|light| std::ptr::eq(light, picked)).unwrap();
+        probabilities[index] = pmf;
+    }
+
+    assert_eq!(probabilities[9], 0.0);
+    assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-6);
+    assert!(tree.sample(point, up, 0.99).is_some());
+}
@@ -0,0 +1,93 @@
+use raytracer::measured::*;
+use raytracer::vec3::Vec3;
+
+const SAMPLES_COUNT: usize = 90 * 90 * 180;
+
+fn merl_bytes(value: f64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(12 + 3 * SAMPLES_COUNT * 8);
+
+    for dim in [90i32, 90, 180] {
+        bytes.extend_from_slice(&dim.to_le_bytes());
+    }
+
+    for _ in 0..3 * SAMPLES_COUNT {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    bytes
+}
+
+fn ggx_data(alpha: f64) -> Vec<f64> {
+    let mut data = Vec::with_capacity(3 * SAMPLES_COUNT);
+
+    for _ in 0..3 {
+        for th in 0..90 {
+            let theta_half = (th as f64 / 90.0).powf(2.0) * std::f64::consts::FRAC_PI_2;
+            let cos2 = theta_half.cos().powf(2.0);
+            let tan2 = (1.0 - cos2) / cos2;
+            let d = 1.0 / (std::f64::consts::PI * alpha * alpha * cos2 * cos2 * (1.0 + tan2 / (alpha * alpha)).powf(2.0));
+            data.extend(std::iter::repeat_n(d, 90 * 180));
+        }
+    }
+
+    data
+}
+
+#[test]
+fn test_measured_from_bytes() {
+    let brdf = MeasuredBrdf::from_bytes(&merl_bytes(1500.0)).unwrap();
+    let v = Vec3::new(0.3, 0.8, 0.1, false).normalized();
+    let l = Vec3::new(-0.5, 0.6, 0.2, false).normalized();
+    let value = brdf.eval(v, l);
+    assert!((value.x() - 1.0).abs() < 1e-9);
+    assert!((value.y() - 1.15).abs() < 1e-9);
+    assert!((value.z() - 1.66).abs() < 1e-9);
+}
+
+#[test]
+fn test_measured_from_bytes_invalid() {
+    assert!(MeasuredBrdf::from_bytes(&[0u8; 8]).is_err());
+
+    let mut bytes = merl_bytes(1.0);
+    bytes.truncate(bytes.len() - 8);
+    assert!(MeasuredBrdf::from_bytes(&bytes).is_err());
+
+    // dimensions whose product overflows
+    let mut bytes = merl_bytes(1.0);
+    for chunk in bytes[..12].chunks_exact_mut(4) {
+        chunk.copy_from_slice(&i32::MAX.to_le_bytes());
+    }
+    assert!(MeasuredBrdf::from_bytes(&bytes).is_err());
+}
+
+#[test]
+fn test_measured_below_surface() {
+    let brdf = MeasuredBrdf::from_data(vec![1.0; 3 * SAMPLES_COUNT]);
+    let v = Vec3::new(0.0, 1.0, 0.0, false);
+    let l = Vec3::new(0.0, -1.0, 0.0, false);
+    assert_eq!(brdf.eval(v, l), Vec3::new(0.0, 0.0, 0.0, false));
+}
+
+#[test]
+fn test_half_diff_angles() {
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    let (theta_half, theta_diff, _) = half_diff_angles(up, up);
+    assert!(theta_half.abs() < 1e-9);
+    assert!(theta_diff.abs() < 1e-9);
+
+    let v = Vec3::new(0.5, 0.5_f64.sqrt(), 0.5, false).normalized();
+    let l = Vec3::new(-0.5, 0.5_f64.sqrt(), -0.5, false).normalized();
+    let (theta_half, theta_diff, _) = half_diff_angles(v, l);
+    assert!(theta_half.abs() < 1e-9);
+    assert!((theta_diff - std::f64::consts::FRAC_PI_4).abs() < 1e-9);
+}
+
+#[test]
+fn test_measured_reciprocity_and_fit() {
+    let brdf = MeasuredBrdf::from_data(ggx_data(0.2));
+    assert!((brdf.fitted_alpha() - 0.2).abs() < 0.02);
+
+    let v = Vec3::new(0.3, 0.8, 0.1, false).normalized();
+    let l = Vec3::new(-0.2, 0.7, 0.3, false).normalized();
+    assert!((brdf.eval(v, l) - brdf.eval(l, v)).length() < 1e-9);
+}
@@ -0,0 +1,52 @@
+use raytracer::medium::MediumStack;
+use raytracer::material::Material;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+fn water() -> Material {
+    Material::dielectric(1.33, 0.0, Color::new(0.0, 0.0, 0.0, false))
+}
+
+fn glass() -> Material {
+    Material {
+        priority: 1,
+        ..Material::dielectric(1.5, 0.0, Color::new(0.0, 0.0, 0.0, false))
+    }
+}
+
+#[test]
+fn test_medium_stack_empty() {
+    let media = MediumStack::new();
+    assert!(media.current().is_none());
+    assert_eq!(media.ior(), 1.0);
+    assert!(!media.is_false_boundary(&water(), true));
+
+    let interface = media.interface(&water(), true);
+    assert_eq!(interface.eta_i, 1.0);
+    assert_eq!(interface.eta_t, 1.33);
+}
+
+#[test]
+fn test_medium_stack_priority() {
+    let media = MediumStack::new()
+        .crossed(&glass(), true)
+        .crossed(&water(), true);
+
+    // water overlapped by the glass wall is a false boundary
+    assert_eq!(media.current(), Some(&glass()));
+    assert!(media.is_false_boundary(&water(), false));
+    assert!(!media.is_false_boundary(&glass(), false));
+
+    // leaving the glass wall lands in the water
+    let interface = media.interface(&glass(), false);
+    assert_eq!(interface.eta_i, 1.5);
+    assert_eq!(interface.eta_t, 1.33);
+}
+
+#[test]
+fn test_medium_stack_entering_lower_priority() {
+    let media = MediumStack::new().crossed(&glass(), true);
+    assert!(media.is_false_boundary(&water(), true));
+    assert!(!media.crossed(&glass(), false).is_false_boundary(&water(), true));
+}
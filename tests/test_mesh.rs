@@ -0,0 +1,216 @@
+use raytracer::mesh::Mesh;
+use raytracer::displacement::{Displacement, Tessellation};
+use raytracer::texture::Texture;
+use raytracer::procedural::{Procedural, Pattern, Space};
+use raytracer::ray::Ray;
+use raytracer::vec3::Vec3;
+use raytracer::hit::{Hit, RayKind, Visibility};
+use raytracer::material::Material;
+use raytracer::light::{Light, Linking};
+use raytracer::lighttree::LightTree;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+const QUAD: &str = "
+# unit quad in the xz plane, facing up
+v 0 0 0
+v 1 0 0
+v 1 0 -1
+v 0 0 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+f 1/1 2/2 3/3 4/4
+";
+
+fn material() -> Material {
+    Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0)
+}
+
+fn down_ray(x: f64, z: f64) -> Ray {
+    Ray::new(Point3::new(x, 5.0, z, true), Vec3::new(0.0, -1.0, 0.0, false))
+}
+
+#[test]
+fn test_obj_quad_hit() {
+    let mesh = Mesh::from_obj(QUAD, material(), None).unwrap();
+    assert_eq!(mesh.triangles.len(), 2);
+    assert_eq!(mesh.positions.len(), 4);
+
+    let record = mesh.hit(&down_ray(0.25, -0.75), 0.001, f64::INFINITY).unwrap();
+    assert!((record.t_min - 5.0).abs() < 1e-9);
+    assert!((record.uv.0 - 0.25).abs() < 1e-9 && (record.uv.1 - 0.75).abs() < 1e-9);
+    assert!((record.normal - Vec3::new(0.0, 1.0, 0.0, false)).length() < 1e-9);
+    assert!(record.front_face);
+    assert!((record.dpdu - Vec3::new(1.0, 0.0, 0.0, false)).length() < 1e-9);
+    assert!((record.dpdv - Vec3::new(0.0, 0.0, -1.0, false)).length() < 1e-9);
+
+    assert!(mesh.hit(&down_ray(1.5, -0.5), 0.001, f64::INFINITY).is_none());
+    assert!(mesh.hit(&down_ray(0.5, -0.5), 0.001, 4.0).is_none());
+}
+
+#[test]
+fn test_obj_indices_and_errors() {
+    let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 -1\nf -3//1 -2//1 -1//1\n";
+    let mesh = Mesh::from_obj(source, material(), None).unwrap();
+    // the normal from the file wins over the winding
+    assert!((mesh.normals[0] - Vec3::new(0.0, 0.0, -1.0, false)).length() < 1e-9);
+
+    assert!(Mesh::from_obj("v 0 0 0\nf 1 2 3\n", material(), None).is_err());
+    assert!(Mesh::from_obj("v 0 0\n", material(), None).is_err());
+    assert!(Mesh::from_obj("v 0 0 0\nv 1 0 0\nf 1 2\n", material(), None).is_err());
+}
+
+/// Grid of `n` by `n` quads over the unit square at height `y(x, z)`.
+fn grid(n: usize, y: impl Fn(f64, f64) -> f64) -> Mesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut triangles = Vec::new();
+
+    for j in 0..=n {
+        for i in 0..=n {
+            let (x, z) = (i as f64 / n as f64, -(j as f64) / n as f64);
+            positions.push(Point3::new(x, y(x, z), z, true));
+            uvs.push((i as f64 / n as f64, j as f64 / n as f64));
+        }
+    }
+
+    for j in 0..n {
+        for i in 0..n {
+            let corner = j * (n + 1) + i;
+            triangles.push([corner, corner + 1, corner + n + 2]);
+            triangles.push([corner, corner + n + 2, corner + n + 1]);
+        }
+    }
+
+    Mesh::new(positions, Vec::new(), uvs, triangles, material(), None)
+}
+
+#[test]
+fn test_bvh_finds_closest_hit() {
+    let height = |x: f64, z: f64| (7.0 * x).sin() * (5.0 * z).cos() * 0.2;
+    let mesh = grid(24, height);
+
+    assert_eq!(mesh.triangles.len(), 24 * 24 * 2);
+    assert!((mesh.bounds().max.y() - mesh.positions.iter().map(|p| p.y()).fold(f64::MIN, f64::max)).abs() < 1e-12);
+
+    for k in 0..50 {
+        let x = 0.013 + 0.019 * k as f64;
+        let z = -0.97 + 0.0191 * k as f64;
+        let record = mesh.hit(&down_ray(x, z), 0.001, f64::INFINITY).unwrap();
+        // piecewise linear, so close to the smooth surface
+        assert!((record.point.y() - height(x, z)).abs() < 0.02);
+    }
+}
+
+#[test]
+fn test_occluded_stops_at_t_max() {
+    let mesh = grid(24, |x, z| (7.0 * x).sin() * (5.0 * z).cos() * 0.2);
+
+    for k in 0..50 {
+        let ray = down_ray(0.013 + 0.019 * k as f64, -0.97 + 0.0191 * k as f64);
+        let t = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap().t_min;
+
+        assert!(mesh.occluded(&ray, 0.001, f64::INFINITY));
+        assert!(mesh.occluded(&ray, 0.001, t + 1e-6));
+        assert!(!mesh.occluded(&ray, 0.001, t - 1e-6));
+    }
+
+    assert!(!mesh.occluded(&down_ray(2.0, 0.5), 0.001, f64::INFINITY));
+}
+
+#[test]
+fn test_tessellation_meets_edge_length() {
+    let mesh = Mesh::from_obj(QUAD, material(), None).unwrap();
+    let mesh = mesh.displaced(&Displacement::Scalar { texture: Texture::Constant(Color::new(0.0, 0.0, 0.0, false)), scale: 1.0 }, Tessellation::EdgeLength(0.2));
+
+    for triangle in &mesh.triangles {
+        for i in 0..3 {
+            let edge = mesh.positions[triangle[(i + 1) % 3]] - mesh.positions[triangle[i]];
+            assert!(edge.length() <= 0.2 + 1e-9);
+        }
+    }
+
+    // split edges share their midpoints, so there are no duplicated vertices
+    for (i, a) in mesh.positions.iter().enumerate() {
+        assert!(mesh.positions[i + 1..].iter().all(|b| (*a - *b).length() > 1e-9));
+    }
+
+    assert!(mesh.hit(&down_ray(0.3, -0.6), 0.001, f64::INFINITY).is_some());
+}
+
+#[test]
+fn test_scalar_displacement_updates_normals_and_bounds() {
+    let ramp = Procedural { space: Space::Uv, ..Procedural::new(Pattern::Gradient, (Color::new(0.0, 0.0, 0.0, false), Color::new(1.0, 1.0, 1.0, false))) };
+    let displacement = Displacement::Scalar { texture: Texture::Procedural(ramp), scale: 0.5 };
+    let mesh = Mesh::from_obj(QUAD, material(), None).unwrap().displaced(&displacement, Tessellation::EdgeLength(0.1));
+
+    // a ramp rising by 0.5 along x
+    assert!((mesh.bounds().max.y() - 0.5).abs() < 1e-9);
+    assert!(mesh.bounds().min.y().abs() < 1e-9);
+
+    let expected = Vec3::new(-0.5, 1.0, 0.0, false).normalized();
+    assert!(mesh.normals.iter().all(|normal| (*normal - expected).length() < 1e-9));
+
+    let record = mesh.hit(&down_ray(0.53, -0.47), 0.001, f64::INFINITY).unwrap();
+    assert!((record.point.y() - 0.265).abs() < 1e-9);
+}
+
+#[test]
+fn test_vector_displacement() {
+    let displacement = Displacement::Vector { texture: Texture::Constant(Color::new(0.0, 1.0, 2.0, false)), scale: 0.5 };
+    let mesh = Mesh::from_obj(QUAD, material(), None).unwrap().displaced(&displacement, Tessellation::EdgeLength(10.0));
+
+    assert_eq!(mesh.triangles.len(), 2);
+    assert!((mesh.bounds().min - Point3::new(0.0, 0.5, 0.0, true)).length() < 1e-9);
+    assert!((mesh.bounds().max - Point3::new(1.0, 0.5, 1.0, true)).length() < 1e-9);
+}
+
+#[test]
+fn test_screen_space_tessellation_is_adaptive() {
+    let flat = Displacement::Scalar { texture: Texture::Constant(Color::new(0.0, 0.0, 0.0, false)), scale: 1.0 };
+    let tessellation = Tessellation::ScreenSpace { eye: Point3::new(0.0, 0.5, 0.0, true), pixel_angle: 0.01, pixels: 8.0 };
+    let mesh = Mesh::from_obj(QUAD, material(), None).unwrap().displaced(&flat, tessellation);
+
+    let edge_near = |corner: Point3| mesh.triangles.iter()
+        .filter(|triangle| triangle.iter().any(|&i| (mesh.positions[i] - corner).length() < 1e-9))
+        .map(|triangle| (mesh.positions[triangle[1]] - mesh.positions[triangle[0]]).length())
+        .fold(f64::MAX, f64::min);
+
+    // triangles close to the eye are finer than far away ones
+    assert!(edge_near(Point3::new(0.0, 0.0, 0.0, true)) < edge_near(Point3::new(1.0, 0.0, -1.0, true)));
+}
+
+#[test]
+fn test_displaced_mesh_keeps_its_name() {
+    let displacement = Displacement::Vector { texture: Texture::Constant(Color::new(0.0, 1.0, 0.0, false)), scale: 0.5 };
+    let mut mesh = Mesh::from_obj(QUAD, material(), None).unwrap();
+    mesh.name = "terrain".to_string();
+    let mesh = mesh.displaced(&displacement, Tessellation::EdgeLength(10.0));
+    assert_eq!(mesh.name(), "terrain");
+
+    // linking still resolves the displaced mesh
+    let mut linked = Light::new(Color::new(1.0, 1.0, 1.0, false), Point3::new(0.5, 5.0, -0.5, true), 0.1);
+    linked.light_linking = Linking::Include(vec!["terrain".to_string()]);
+    linked.shadow_linking = Linking::Exclude(vec!["terrain".to_string()]);
+    assert!(!linked.shadow_linking.includes(mesh.name()));
+
+    let tree = LightTree::new(vec![linked]);
+    let up = Vec3::new(0.0, 1.0, 0.0, false);
+    assert!(tree.sample_linked(mesh.name(), Point3::new(0.5, 0.5, -0.5, true), up, 0.5).is_some());
+    assert!(tree.sample_linked("", Point3::new(0.5, 0.5, -0.5, true), up, 0.5).is_none());
+}
+
+#[test]
+fn test_displaced_mesh_keeps_its_visibility() {
+    let displacement = Displacement::Vector { texture: Texture::Constant(Color::new(0.0, 1.0, 0.0, false)), scale: 0.5 };
+    let mut mesh = Mesh::from_obj(QUAD, material(), None).unwrap();
+    mesh.visibility = Visibility { camera: false, shadow: false, ..Visibility::default() };
+    let mesh = mesh.displaced(&displacement, Tessellation::EdgeLength(10.0));
+
+    assert!(!mesh.visibility().is_visible(RayKind::Camera));
+    assert!(!mesh.visibility().is_visible(RayKind::Shadow));
+    assert!(mesh.visibility().is_visible(RayKind::Reflection));
+}
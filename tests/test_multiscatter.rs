@@ -0,0 +1,75 @@
+use raytracer::multiscatter::*;
+use raytracer::brdf::{brdf, pdf, sample_brdf};
+use raytracer::frame::Frame;
+use raytracer::material::Material;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+fn furnace(material: Material, cos_theta_v: f64) -> f64 {
+    let frame = Frame::from_normal(Vec3::new(0.0, 1.0, 0.0, false));
+    let v = Vec3::new((1.0 - cos_theta_v * cos_theta_v).sqrt(), cos_theta_v, 0.0, false);
+    let samples_count = 50000;
+    let mut sum = 0.0;
+
+    for _ in 0..samples_count {
+        if let Some(l) = sample_brdf(&material, &frame, v) {
+            let pdf = pdf(&material, &frame, v, l);
+
+            if pdf > 0.0 {
+                sum += brdf(&material, &frame, v, l).y() * l.y() / pdf;
+            }
+        }
+    }
+
+    sum / samples_count as f64
+}
+
+#[test]
+fn test_ggx_albedo_loses_energy_with_roughness() {
+    assert!(ggx_albedo(0.8, 0.01) > 0.99);
+    assert!(ggx_albedo(0.8, 1.0) < 0.8);
+    assert!(ggx_average_albedo(0.2) > ggx_average_albedo(0.8));
+}
+
+#[test]
+fn test_dielectric_albedo_smooth() {
+    assert!((dielectric_albedo(0.7, 0.01, 1.0 / 1.5) - 1.0).abs() < 0.01);
+    assert!((dielectric_albedo(0.7, 0.01, 1.5) - 1.0).abs() < 0.01);
+    assert!(dielectric_albedo(0.7, 1.0, 1.0 / 1.5) < 1.0);
+}
+
+#[test]
+fn test_average_fresnel() {
+    let white = average_fresnel(|_| Color::new(1.0, 1.0, 1.0, false));
+    assert!((white.x() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_white_furnace() {
+    let white = Color::new(1.0, 1.0, 1.0, false);
+
+    for roughness in [0.1, 0.4, 0.7, 1.0] {
+        for cos_theta_v in [0.9, 0.5] {
+            let albedo = furnace(Material::new(white, roughness, 1.0), cos_theta_v);
+            assert!((albedo - 1.0).abs() < 0.03, "albedo {} at roughness {}", albedo, roughness);
+        }
+    }
+}
+
+#[test]
+fn test_furnace_does_not_gain_energy() {
+    let white = Color::new(1.0, 1.0, 1.0, false);
+
+    // the compensation lobe must not push dielectric and partly metallic
+    // surfaces, whose diffuse lobe takes the energy specular leaves, above one
+    for metallic in [0.0, 0.5] {
+        for roughness in [0.1, 0.4, 0.7, 1.0] {
+            for cos_theta_v in [0.9, 0.5, 0.2] {
+                let material = Material { specular_tint: 0.5, ..Material::new(white, roughness, metallic) };
+                let albedo = furnace(material, cos_theta_v);
+                assert!(albedo > 0.4 && albedo < 1.01, "albedo {} at roughness {} and metallic {}", albedo, roughness, metallic);
+            }
+        }
+    }
+}
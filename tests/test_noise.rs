@@ -0,0 +1,47 @@
+use raytracer::noise::*;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+
+fn points() -> impl Iterator<Item = Point3> {
+    (0..2000).map(|i| {
+        let i = i as f64;
+        Point3::new((i * 0.37).sin() * 9.3, (i * 0.71).cos() * 5.1 - 2.0, i * 0.013 - 7.0, true)
+    })
+}
+
+#[test]
+fn test_perlin_vanishes_on_lattice() {
+    for (x, y, z) in [(0.0, 0.0, 0.0), (3.0, -2.0, 7.0), (-11.0, 4.0, -1.0)] {
+        assert!(perlin(Point3::new(x, y, z, true)).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_noise_range() {
+    for p in points() {
+        assert!(perlin(p).abs() <= 1.0);
+        assert!(simplex(p).abs() <= 1.0);
+        assert!((0.0..=1.0).contains(&turbulence(Basis::Simplex, p, 4)));
+    }
+}
+
+#[test]
+fn test_noise_is_continuous() {
+    let offset = Vec3::new(1e-6, 1e-6, 1e-6, false);
+
+    for p in points() {
+        assert!((perlin(p) - perlin(p + offset)).abs() < 1e-4);
+        assert!((simplex(p) - simplex(p + offset)).abs() < 1e-4);
+        assert!((fbm(Basis::Perlin, p, 5) - fbm(Basis::Perlin, p + offset, 5)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn test_worley_distance() {
+    for p in points() {
+        let distance = worley(p);
+        // the feature point of the cell containing p is at most a diagonal away
+        assert!((0.0..=3.0_f64.sqrt()).contains(&distance));
+    }
+}
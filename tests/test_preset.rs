@@ -0,0 +1,51 @@
+use raytracer::preset::Preset;
+use raytracer::brdf::{fresnel_conductor, fresnel_dielectric};
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-10;
+
+#[test]
+fn test_preset_from_str() {
+    assert_eq!("gold".parse::<Preset>(), Ok(Preset::Gold));
+    assert_eq!(" Aluminum ".parse::<Preset>(), Ok(Preset::Aluminium));
+    assert_eq!("rubber".parse::<Preset>(), Ok(Preset::Rubber));
+    assert!("unobtainium".parse::<Preset>().is_err());
+}
+
+#[test]
+fn test_preset_materials() {
+    let gold = Preset::Gold.material(0.2);
+    assert_eq!(gold.metallic, 1.0);
+    assert!(gold.conductor.is_some());
+    assert!(gold.albedo.x() > gold.albedo.z());
+
+    let silver = Preset::Silver.material(0.2);
+    assert!(silver.albedo.x() > 0.9 && silver.albedo.z() > 0.9);
+
+    let plastic = Preset::Plastic.material(0.2);
+    assert_eq!(plastic.metallic, 0.0);
+    assert!(plastic.conductor.is_none());
+}
+
+#[test]
+fn test_fresnel_conductor() {
+    let conductor = Preset::Copper.conductor().unwrap();
+    let f0 = fresnel_conductor(1.0, conductor.eta, conductor.k);
+    assert!((f0 - conductor.f0()).length() < 1e-9);
+
+    let grazing = fresnel_conductor(0.0, conductor.eta, conductor.k);
+    assert!((grazing - Color::new(1.0, 1.0, 1.0, false)).length() < 1e-9);
+}
+
+#[test]
+fn test_fresnel_conductor_without_absorption() {
+    let eta = Color::new(1.5, 1.5, 1.5, false);
+    let k = Color::new(0.0, 0.0, 0.0, false);
+
+    for cos_theta in [0.1, 0.5, 0.9] {
+        let conductor = fresnel_conductor(cos_theta, eta, k);
+        assert!((conductor.x() - fresnel_dielectric(cos_theta, 1.0 / 1.5)).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,82 @@
+use raytracer::procedural::*;
+use raytracer::noise::Basis;
+use raytracer::hit::HitRecord;
+use raytracer::material::Material;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-12;
+
+fn black_and_white() -> (Color, Color) {
+    (Color::new(0.0, 0.0, 0.0, false), Color::new(1.0, 1.0, 1.0, false))
+}
+
+fn hit_record(material: &Material, point: Point3, object_point: Point3) -> HitRecord<'_> {
+    HitRecord {
+        t_min: 1.0,
+        point,
+        point_error: Vec3::new(0.0, 0.0, 0.0, false),
+        object_point,
+        normal: Vec3::new(0.0, 1.0, 0.0, false),
+        geometric_normal: Vec3::new(0.0, 1.0, 0.0, false),
+        uv: (0.25, 0.75),
+        dpdu: Vec3::new(1.0, 0.0, 0.0, false),
+        dpdv: Vec3::new(0.0, 0.0, 1.0, false),
+        dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+        dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+        duvdx: (0.0, 0.0),
+        duvdy: (0.0, 0.0),
+        front_face: true,
+        material,
+        name: ""
+    }
+}
+
+#[test]
+fn test_checker_and_stripes() {
+    assert_eq!(Pattern::Checker.eval(Point3::new(0.5, 0.5, 0.5, true)), 0.0);
+    assert_eq!(Pattern::Checker.eval(Point3::new(1.5, 0.5, 0.5, true)), 1.0);
+    assert_eq!(Pattern::Checker.eval(Point3::new(-0.5, 0.5, 0.5, true)), 1.0);
+    assert_eq!(Pattern::Stripes.eval(Point3::new(2.5, 9.0, -3.0, true)), 0.0);
+    assert_eq!(Pattern::Stripes.eval(Point3::new(-2.5, 9.0, -3.0, true)), 1.0);
+}
+
+#[test]
+fn test_patterns_in_unit_range() {
+    let patterns = [
+        Pattern::Noise { basis: Basis::Perlin, octaves: 4 },
+        Pattern::Turbulence { basis: Basis::Simplex, octaves: 4 },
+        Pattern::Marble { turbulence: 5.0, octaves: 4 },
+        Pattern::Wood { rings: 4.0, turbulence: 0.2 }
+    ];
+
+    for pattern in patterns {
+        for i in 0..500 {
+            let i = i as f64;
+            let t = pattern.eval(Point3::new(i.sin() * 4.0, i * 0.01, i.cos() * 3.0, true));
+            assert!((0.0..=1.0).contains(&t));
+        }
+    }
+}
+
+#[test]
+fn test_space_scale_and_offset() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material, Point3::new(0.8, 0.0, 0.0, true), Point3::new(0.2, 0.0, 0.0, true));
+    let mut gradient = Procedural::new(Pattern::Gradient, black_and_white());
+
+    assert!((gradient.value(&record).x() - 0.2).abs() < EPSILON);
+
+    gradient.space = Space::World;
+    assert!((gradient.value(&record).x() - 0.8).abs() < EPSILON);
+
+    gradient.space = Space::Uv;
+    gradient.scale = Vec3::new(2.0, 1.0, 1.0, false);
+    gradient.offset = Vec3::new(-0.1, 0.0, 0.0, false);
+    assert!((gradient.value(&record).x() - 0.4).abs() < EPSILON);
+
+    gradient.offset = Vec3::new(3.0, 0.0, 0.0, false);
+    assert!((gradient.value(&record).x() - 1.0).abs() < EPSILON);
+}
@@ -1,4 +1,4 @@
-use raytracer::ray::Ray;
+use raytracer::ray::{Ray, Differentials};
 use raytracer::vec3::Vec3;
 use raytracer::transform::*;
 
@@ -89,4 +89,29 @@ fn test_ray_transform_z_rotation() {
     let expected_direction = Vec3::new(-5.0, 4.0, 6.0, false);
     assert!(approx_eq(transformed_ray.origin(), expected_origin));
     assert!(approx_eq(transformed_ray.direction(), expected_direction));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_ray_differentials_reflected() {
+    let differentials = Differentials {
+        rx_origin: Vec3::new(0.1, 1.0, 0.0, true),
+        rx_direction: Vec3::new(0.1, -1.0, 0.0, false),
+        ry_origin: Vec3::new(0.0, 1.0, 0.1, true),
+        ry_direction: Vec3::new(0.0, -1.0, 0.1, false)
+    };
+    let point = Vec3::new(0.0, 0.0, 0.0, true);
+    let normal = Vec3::new(0.0, 1.0, 0.0, false);
+    let dpdx = Vec3::new(0.1, 0.0, 0.0, false);
+    let dpdy = Vec3::new(0.0, 0.0, 0.1, false);
+
+    let reflected = differentials.reflected(point, normal, dpdx, dpdy);
+    assert!(approx_eq(reflected.rx_origin, Vec3::new(0.1, 0.0, 0.0, true)));
+    assert!(approx_eq(reflected.rx_direction, Vec3::new(0.1, 1.0, 0.0, false)));
+    assert!(approx_eq(reflected.ry_direction, Vec3::new(0.0, 1.0, 0.1, false)));
+
+    let ray = Ray::with_differentials(point, normal, Some(differentials));
+    let translated = ray.transform(&translation_matrix(&Vec3::new(1.0, 0.0, 0.0, false)).mat);
+    let moved = translated.differentials().unwrap();
+    assert!(approx_eq(moved.rx_origin, Vec3::new(1.1, 1.0, 0.0, true)));
+    assert!(approx_eq(moved.rx_direction, differentials.rx_direction));
+}
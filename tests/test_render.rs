@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use raytracer::render::trace_ray;
+use raytracer::scene::Scene;
+use raytracer::background::{Background, EnvironmentMap};
+use raytracer::sky::Sky;
+use raytracer::box3::Box3;
+use raytracer::image::Image;
+use raytracer::hit::Visibility;
+use raytracer::light::{Light, Linking};
+use raytracer::material::Material;
+use raytracer::ray::Ray;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+fn floor_scene(background: Background) -> Scene {
+    let floor = Box3::new(
+        Point3::new(-100.0, -1.0, -100.0, true),
+        Point3::new(100.0, 0.0, 100.0, true),
+        Material::new(Color::new(0.5, 0.5, 0.5, false), 0.6, 0.0),
+        None
+    );
+    let light = Light::new(Color::new(0.0, 0.0, 0.0, false), Point3::new(0.0, 10.0, 0.0, true), 0.1);
+    let mut scene = Scene::new(vec![Box::new(floor)], vec![light]);
+    scene.background = background;
+    scene
+}
+
+fn average_radiance(scene: &Scene, samples: usize) -> f64 {
+    let ray = Ray::new(Point3::new(0.0, 1.0, 1.0, true), Vec3::new(0.0, -1.0, -1.0, false));
+    (0..samples).map(|_| trace_ray(scene, &ray, 2).y()).sum::<f64>() / samples as f64
+}
+
+#[test]
+fn test_missed_rays_see_background() {
+    let scene = floor_scene(Background::Gradient { bottom: Color::new(0.0, 0.0, 0.0, false), top: Color::new(0.0, 1.0, 0.0, false) });
+    let up = Ray::new(Point3::new(0.0, 1.0, 0.0, true), Vec3::new(0.0, 1.0, 0.0, false));
+    assert!((trace_ray(&scene, &up, 2).y() - 1.0).abs() < 1e-12);
+    assert_eq!(trace_ray(&scene, &up, 0).y(), 0.0);
+}
+
+#[test]
+fn test_environment_sampling_matches_constant_background() {
+    // a uniform environment map must light the floor like the same constant
+    // background, which is only reached by brdf sampling
+    let white = Color::new(1.0, 1.0, 1.0, false);
+    let map = EnvironmentMap::new(Arc::new(Image::new(8, 4, vec![white; 32])), 0.0, 1.0);
+
+    let sampled = average_radiance(&floor_scene(Background::Environment(map)), 4000);
+    let reference = average_radiance(&floor_scene(Background::Color(white)), 4000);
+
+    assert!(reference > 0.1);
+    assert!((sampled - reference).abs() < 0.03 * reference, "{} != {}", sampled, reference);
+}
+
+#[test]
+fn test_sky_lights_floor() {
+    let scene = floor_scene(Background::Sky(Sky::new(60.0, 180.0, 3.0)));
+    let radiance = average_radiance(&scene, 200);
+
+    assert!(radiance.is_finite() && radiance > 0.0);
+}
+
+/// Floor under a box blocking a light, in the dark.
+fn blocked_scene(light_linking: Linking, shadow_linking: Linking, visibility: Visibility) -> Scene {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.6, 0.0);
+    let mut floor = Box3::new(Point3::new(-100.0, -1.0, -100.0, true), Point3::new(100.0, 0.0, 100.0, true), material.clone(), None);
+    let mut blocker = Box3::new(Point3::new(-2.0, 2.0, -2.0, true), Point3::new(2.0, 3.0, 2.0, true), material, None);
+    floor.name = "floor".to_string();
+    blocker.name = "blocker".to_string();
+    blocker.visibility = visibility;
+
+    let mut light = Light::new(Color::new(1.0, 1.0, 1.0, false), Point3::new(0.0, 10.0, 0.0, true), 0.1);
+    light.light_linking = light_linking;
+    light.shadow_linking = shadow_linking;
+
+    let mut scene = Scene::new(vec![Box::new(floor), Box::new(blocker)], vec![light]);
+    scene.background = Background::Color(Color::new(0.0, 0.0, 0.0, false));
+    scene
+}
+
+#[test]
+fn test_light_and_shadow_linking() {
+    let shadowed = average_radiance(&blocked_scene(Linking::All, Linking::All, Visibility::default()), 50);
+    let unshadowed = average_radiance(&blocked_scene(Linking::All, Linking::Exclude(vec!["blocker".to_string()]), Visibility::default()), 50);
+    let unlinked = average_radiance(&blocked_scene(Linking::Include(vec!["blocker".to_string()]), Linking::All, Visibility::default()), 50);
+
+    assert!(shadowed.abs() < 1e-9);
+    assert!(unshadowed > 0.1);
+    assert!(unlinked.abs() < 1e-9);
+}
+
+#[test]
+fn test_visibility_flags() {
+    // looking down at the floor through the blocker
+    let ray = Ray::new(Point3::new(0.0, 5.0, 0.0, true), Vec3::new(0.0, -1.0, 0.0, false));
+    let render = |visibility: Visibility| {
+        let scene = blocked_scene(Linking::All, Linking::All, visibility);
+        (0..50).map(|_| trace_ray(&scene, &ray, 2).y()).sum::<f64>() / 50.0
+    };
+
+    // the blocker's lit top is seen first
+    assert!(render(Visibility::default()) > 0.1);
+
+    // hidden from the camera, it still shadows the floor
+    let hidden = Visibility { camera: false, ..Visibility::default() };
+    assert!(render(hidden).abs() < 1e-9);
+
+    let unshadowing = Visibility { camera: false, shadow: false, ..Visibility::default() };
+    assert!(render(unshadowing) > 0.1);
+}
+
+#[test]
+fn test_shadows_end_at_the_light() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.6, 0.0);
+    let floor = Box3::new(Point3::new(-100.0, -1.0, -100.0, true), Point3::new(100.0, 0.0, 100.0, true), material.clone(), None);
+    let ceiling = Box3::new(Point3::new(-100.0, 12.0, -100.0, true), Point3::new(100.0, 13.0, 100.0, true), material, None);
+    let light = Light::new(Color::new(1.0, 1.0, 1.0, false), Point3::new(0.0, 10.0, 0.0, true), 0.1);
+    let mut scene = Scene::new(vec![Box::new(floor), Box::new(ceiling)], vec![light]);
+    scene.background = Background::Color(Color::new(0.0, 0.0, 0.0, false));
+
+    // the ceiling above the light does not shadow the floor
+    let up = Ray::new(Point3::new(0.0, 0.0, 0.0, true), Vec3::new(0.0, 1.0, 0.0, false));
+    assert!(scene.occluded(&up, f64::INFINITY));
+    assert!(!scene.occluded(&up, 10.0));
+    assert!(average_radiance(&scene, 20) > 0.1);
+}
+
+#[test]
+fn test_rough_glass_does_not_gain_energy() {
+    // a white furnace, where a rough slab may only lose the energy of the
+    // paths cut short by the depth limit
+    let glass = Box3::new(
+        Point3::new(-100.0, -0.01, -100.0, true),
+        Point3::new(100.0, 0.0, 100.0, true),
+        Material::dielectric(1.5, 0.8, Color::new(0.0, 0.0, 0.0, false)),
+        None
+    );
+    let light = Light::new(Color::new(0.0, 0.0, 0.0, false), Point3::new(0.0, 10.0, 0.0, true), 0.1);
+    let mut scene = Scene::new(vec![Box::new(glass)], vec![light]);
+    scene.background = Background::Color(Color::new(1.0, 1.0, 1.0, false));
+
+    let grazing = Ray::new(Point3::new(0.0, 0.1, 1.0, true), Vec3::new(0.0, -0.1, -1.0, false));
+    let samples = 200;
+    let radiance = (0..samples).map(|_| trace_ray(&scene, &grazing, 5).y()).sum::<f64>() / samples as f64;
+
+    assert!(radiance > 0.5 && radiance < 1.02, "{}", radiance);
+}
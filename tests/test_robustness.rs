@@ -0,0 +1,254 @@
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use proptest::prelude::*;
+
+use raytracer::aabb::Aabb;
+use raytracer::box3::Box3;
+use raytracer::sphere::Sphere;
+use raytracer::mesh::Mesh;
+use raytracer::hit::Hit;
+use raytracer::ray::{Ray, RAY_T_MIN};
+use raytracer::material::Material;
+use raytracer::math::transpose;
+use raytracer::transform::{Transform, translation_matrix, scaling_matrix, x_rotation_matrix, y_rotation_matrix};
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+fn material() -> Material {
+    Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0)
+}
+
+/// Unit direction from spherical angles.
+fn unit(theta: f64, phi: f64) -> Vec3 {
+    Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin(), false)
+}
+
+fn direction(v: Vec3) -> Vec3 {
+    Vec3::new(v.x(), v.y(), v.z(), false)
+}
+
+/// Axis-aligned box of size `scale` centered on `center`.
+fn scaled_box(center: Point3, scale: f64) -> Box3 {
+    let half = Vec3::new(0.5 * scale, 0.3 * scale, 0.4 * scale, false);
+    Box3::new(center - half, center + half, material(), None)
+}
+
+/// Point on an edge (`corner` false) or at a corner of the box, the edge
+/// running along `axis` at the fraction `s` of its length.
+fn box_edge_point(box3: &Box3, axis: usize, s: f64, signs: (bool, bool), corner: bool) -> Point3 {
+    let pick = |i: usize, high: bool| if high { box3.max_bound[i] } else { box3.min_bound[i] };
+    let along = if corner { pick(axis, s > 0.5) } else { box3.min_bound[axis] + s * (box3.max_bound[axis] - box3.min_bound[axis]) };
+    let mut coordinates = [0.0; 3];
+    coordinates[axis] = along;
+    coordinates[(axis + 1) % 3] = pick((axis + 1) % 3, signs.0);
+    coordinates[(axis + 2) % 3] = pick((axis + 2) % 3, signs.1);
+
+    Point3::new(coordinates[0], coordinates[1], coordinates[2], true)
+}
+
+fn is_axis_aligned(normal: Vec3) -> bool {
+    let components = [normal.x(), normal.y(), normal.z()];
+    components.iter().filter(|c| c.abs() == 1.0).count() == 1 && components.iter().filter(|c| **c == 0.0).count() == 2
+}
+
+proptest! {
+    #[test]
+    fn box_edges_and_corners_are_hit(
+        exponent in -6.0..6.0f64,
+        axis in 0..3usize,
+        s in 0.0..1.0f64,
+        signs in (any::<bool>(), any::<bool>()),
+        corner in any::<bool>(),
+        theta in 0.01..PI - 0.01,
+        phi in 0.0..TAU
+    ) {
+        let scale = 10f64.powf(exponent);
+        let box3 = scaled_box(Point3::new(3.0, -2.0, 1.0, true) * scale, scale);
+        let target = box_edge_point(&box3, axis, s, signs, corner);
+        let origin = target + unit(theta, phi) * (5.0 * scale);
+        let ray = Ray::new(origin, direction(target - origin));
+
+        // the edge is reached at t = 1, unless the ray entered the box before
+        let record = box3.hit(&ray, RAY_T_MIN, f64::INFINITY);
+        prop_assert!(record.is_some());
+        let record = record.unwrap();
+        prop_assert!(record.t_min <= 1.0 + 1e-9);
+        prop_assert!(is_axis_aligned(record.normal), "{:?}", record.normal);
+        prop_assert!(!record.point.x().is_nan() && !record.point.y().is_nan() && !record.point.z().is_nan());
+    }
+
+    #[test]
+    fn rays_in_a_slab_plane_hit_conservatively(y in -1.0..1.0f64, z in -1.0..1.0f64, phi in 0.0..TAU) {
+        let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0, true), Point3::new(1.0, 1.0, 1.0, true));
+        let box3 = Box3::new(aabb.min, aabb.max, material(), None);
+
+        // lying in the plane x = -1, where 0 * inf would give NaN
+        let origin = Point3::new(-1.0, y, z, true) - Vec3::new(0.0, phi.cos(), phi.sin(), false) * 4.0;
+        let ray = Ray::new(origin, Vec3::new(0.0, phi.cos(), phi.sin(), false));
+        prop_assert!(aabb.hit(&ray, 0.0, f64::INFINITY));
+
+        if let Some(record) = box3.hit(&ray, RAY_T_MIN, f64::INFINITY) {
+            prop_assert!(is_axis_aligned(record.normal));
+            prop_assert!(record.t_min.is_finite());
+        }
+    }
+
+    #[test]
+    fn grazing_rays_hit_spheres_accurately(
+        exponent in -6.0..6.0f64,
+        gap_exponent in -9.0..-1.0f64,
+        theta in 0.01..PI - 0.01,
+        phi in 0.0..TAU
+    ) {
+        let radius = 10f64.powf(exponent);
+        let center = Point3::new(100.0, -40.0, 70.0, true) * radius;
+        let sphere = Sphere::new(center, radius, material(), None);
+
+        // aimed at a distance just under and over the radius from the center
+        let along = unit(theta, phi);
+        let across = along.orthonormal_basis().0;
+        let graze = |gap: f64| {
+            let origin = center + across * (radius * (1.0 + gap)) - along * (10.0 * radius);
+            sphere.hit(&Ray::new(origin, along), RAY_T_MIN, f64::INFINITY)
+        };
+
+        let record = graze(-(10f64.powf(gap_exponent)));
+        prop_assert!(record.is_some());
+        let distance = direction(record.unwrap().point - center).length();
+        prop_assert!((distance - radius).abs() <= 1e-12 * radius);
+        prop_assert!(graze(1e-6).is_none());
+    }
+
+    #[test]
+    fn spawned_rays_leave_convex_surfaces(
+        exponent in -6.0..6.0f64,
+        theta in 0.01..PI - 0.01,
+        phi in 0.0..TAU,
+        out_theta in 0.0..FRAC_PI_2,
+        out_phi in 0.0..TAU,
+        is_sphere in any::<bool>()
+    ) {
+        let scale = 10f64.powf(exponent);
+        let center = Point3::new(-30.0, 12.0, 5.0, true) * scale;
+        let object: Box<dyn Hit> = if is_sphere {
+            Box::new(Sphere::new(center, 0.5 * scale, material(), None))
+        } else {
+            Box::new(scaled_box(center, scale))
+        };
+
+        let origin = center + unit(theta, phi) * (4.0 * scale);
+        let record = object.hit(&Ray::new(origin, direction(center - origin)), RAY_T_MIN, f64::INFINITY).unwrap();
+        let (tangent, bitangent) = record.geometric_normal.orthonormal_basis();
+        let local = unit(out_theta, out_phi);
+        let outward = tangent * local.x() + record.geometric_normal * local.y() + bitangent * local.z();
+
+        // leaving, down to grazing angles, never hits the surface again
+        let leaving = Ray::new(record.spawn_origin(outward), outward);
+        prop_assert!(object.hit(&leaving, RAY_T_MIN, f64::INFINITY).is_none());
+
+        // entering finds the far side, not the surface just crossed
+        let inward = -record.geometric_normal;
+        let entering = Ray::new(record.spawn_origin(inward), inward);
+        let far_side = object.hit(&entering, RAY_T_MIN, f64::INFINITY);
+        prop_assert!(far_side.is_some());
+        prop_assert!(far_side.unwrap().t_min > 0.1 * scale);
+    }
+
+    #[test]
+    fn spawned_rays_leave_large_spheres_near_the_origin(
+        exponent in 0.0..6.0f64,
+        x in -1e-3..1e-3f64,
+        z in -1e-3..1e-3f64,
+        out_theta in 0.0..FRAC_PI_2,
+        out_phi in 0.0..TAU
+    ) {
+        // the error of the hit comes from the sphere, not from the point
+        let radius = 10f64.powf(exponent);
+        let sphere = Sphere::new(Point3::new(0.0, -radius, 0.0, true), radius, material(), None);
+        let down = Vec3::new(0.0, -1.0, 0.0, false);
+        let record = sphere.hit(&Ray::new(Point3::new(x, 1.0, z, true), down), RAY_T_MIN, f64::INFINITY).unwrap();
+
+        let (tangent, bitangent) = record.geometric_normal.orthonormal_basis();
+        let local = unit(out_theta, out_phi);
+        let outward = tangent * local.x() + record.geometric_normal * local.y() + bitangent * local.z();
+        let leaving = Ray::new(record.spawn_origin(outward), outward);
+        prop_assert!(sphere.hit(&leaving, RAY_T_MIN, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn spawned_rays_leave_transformed_boxes(
+        exponent in -3.0..3.0f64,
+        angles in (0.0..360.0f64, 0.0..360.0f64),
+        theta in 0.01..PI - 0.01,
+        phi in 0.0..TAU,
+        out_theta in 0.0..FRAC_PI_2,
+        out_phi in 0.0..TAU
+    ) {
+        let scale = 10f64.powf(exponent);
+        let matrix = translation_matrix(&Vec3::new(250.0, -80.0, 40.0, false))
+            * x_rotation_matrix(angles.0)
+            * y_rotation_matrix(angles.1)
+            * scaling_matrix(scale, 2.0 * scale, 0.5 * scale);
+        let box3 = scaled_box(Point3::new(0.0, 0.0, 0.0, true), 1.0);
+        let normal_matrix = transpose(&matrix.inv);
+        let center = Point3::new(0.0, 0.0, 0.0, true).transform(&matrix.mat);
+
+        // intersected in object space, then moved to world space as the
+        // renderer does
+        let world_hit = |ray: &Ray| {
+            box3.hit(&ray.transform(&matrix.inv), RAY_T_MIN, f64::INFINITY).map(|mut record| {
+                record.transform_point(&matrix.mat);
+                record.geometric_normal = record.geometric_normal.transform(&normal_matrix).normalized();
+                record
+            })
+        };
+
+        let origin = center + unit(theta, phi) * (10.0 * scale);
+        let record = world_hit(&Ray::new(origin, direction(center - origin))).unwrap();
+        let (tangent, bitangent) = record.geometric_normal.orthonormal_basis();
+        let local = unit(out_theta, out_phi);
+        let outward = tangent * local.x() + record.geometric_normal * local.y() + bitangent * local.z();
+
+        prop_assert!(world_hit(&Ray::new(record.spawn_origin(outward), outward)).is_none());
+    }
+
+    #[test]
+    fn meshes_are_watertight(i in 1..7usize, j in 1..7usize, edge in 0..4usize, tilt in 0.0..0.5f64, phi in 0.0..TAU) {
+        let n = 8;
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+
+        for row in 0..=n {
+            for column in 0..=n {
+                positions.push(Point3::new(column as f64 * 0.1, 0.0, -(row as f64) * 0.1, true));
+            }
+        }
+
+        for row in 0..n {
+            for column in 0..n {
+                let corner = row * (n + 1) + column;
+                triangles.push([corner, corner + 1, corner + n + 2]);
+                triangles.push([corner, corner + n + 2, corner + n + 1]);
+            }
+        }
+
+        let mesh = Mesh::new(positions, Vec::new(), Vec::new(), triangles, material(), None);
+
+        // an inner vertex, or the middle of a horizontal, vertical or diagonal
+        // edge
+        let (x, z) = (i as f64 * 0.1, -(j as f64) * 0.1);
+        let target = match edge {
+            0 => Point3::new(x, 0.0, z, true),
+            1 => Point3::new(x + 0.05, 0.0, z, true),
+            2 => Point3::new(x, 0.0, z - 0.05, true),
+            _ => Point3::new(x + 0.05, 0.0, z - 0.05, true)
+        };
+        let down = -unit(tilt, phi);
+        let ray = Ray::new(target - down * 3.0, down);
+
+        prop_assert!(mesh.hit(&ray, RAY_T_MIN, f64::INFINITY).is_some());
+        prop_assert!(mesh.occluded(&ray, RAY_T_MIN, f64::INFINITY));
+    }
+}
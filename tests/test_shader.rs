@@ -0,0 +1,128 @@
+use raytracer::shader::*;
+use raytracer::texture::{Texture, TextureCache};
+use raytracer::procedural::Space;
+use raytracer::material::{Material, Parameter};
+use raytracer::brdf::fresnel_dielectric;
+use raytracer::hit::HitRecord;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Point3;
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-12;
+
+fn hit_record(material: &Material) -> HitRecord<'_> {
+    HitRecord {
+        t_min: 1.0,
+        point: Point3::new(0.25, 2.0, 0.0, true),
+        point_error: Vec3::new(0.0, 0.0, 0.0, false),
+        object_point: Point3::new(0.25, 0.0, 0.0, true),
+        normal: Vec3::new(0.0, 1.0, 0.0, false),
+        geometric_normal: Vec3::new(0.0, 1.0, 0.0, false),
+        uv: (0.5, 0.5),
+        dpdu: Vec3::new(1.0, 0.0, 0.0, false),
+        dpdv: Vec3::new(0.0, 0.0, 1.0, false),
+        dpdx: Vec3::new(0.0, 0.0, 0.0, false),
+        dpdy: Vec3::new(0.0, 0.0, 0.0, false),
+        duvdx: (0.0, 0.0),
+        duvdy: (0.0, 0.0),
+        front_face: true,
+        material,
+        name: ""
+    }
+}
+
+fn view() -> Vec3 {
+    Vec3::new(0.6, 0.8, 0.0, false)
+}
+
+#[test]
+fn test_arithmetic_nodes() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material);
+    let red = Node::Constant(Color::new(1.0, 0.0, 0.0, false));
+    let tint = Node::Constant(Color::new(0.5, 0.5, 0.5, false));
+
+    let product = Node::Multiply(Box::new(red.clone()), Box::new(tint.clone())).eval(&record, view());
+    assert_eq!(product, Color::new(0.5, 0.0, 0.0, false));
+
+    let sum = Node::Add(Box::new(red.clone()), Box::new(tint)).eval(&record, view());
+    assert_eq!(sum, Color::new(1.5, 0.5, 0.5, false));
+
+    let inverted = Node::Invert(Box::new(red.clone())).eval(&record, view());
+    assert_eq!(inverted, Color::new(0.0, 1.0, 1.0, false));
+
+    let mixed = Node::mix(red, Node::constant(0.0), Node::constant(0.25)).eval(&record, view());
+    assert!((mixed.x() - 0.75).abs() < EPSILON);
+}
+
+#[test]
+fn test_ramp_and_inputs() {
+    let material = Material::new(Color::new(0.5, 0.5, 0.5, false), 0.5, 0.0);
+    let record = hit_record(&material);
+    let stops = vec![(0.0, Color::new(0.0, 0.0, 0.0, false)), (0.5, Color::new(1.0, 0.0, 0.0, false))];
+
+    let object = Node::Ramp(Box::new(Node::Position(Space::Object)), stops.clone()).eval(&record, view());
+    assert!((object.x() - 0.5).abs() < EPSILON);
+
+    let world = Node::Ramp(Box::new(Node::Position(Space::World)), stops).eval(&record, view());
+    assert!((world.x() - 0.5).abs() < EPSILON);
+
+    assert!((Node::Facing.eval(&record, view()).x() - 0.8).abs() < EPSILON);
+    assert!((Node::Fresnel(1.5).eval(&record, view()).x() - fresnel_dielectric(0.8, 1.0 / 1.5)).abs() < EPSILON);
+    assert_eq!(Node::Normal.eval(&record, view()), record.normal);
+}
+
+#[test]
+fn test_parse() {
+    let mut cache = TextureCache::new();
+    let node = parse("mix(rgb(1, 0, 0), 0.5, ramp(facing, 0.0, 0, 1.0, rgb(1,1,1)))", &mut cache).unwrap();
+
+    let expected = Node::mix(
+        Node::Constant(Color::new(1.0, 0.0, 0.0, false)),
+        Node::constant(0.5),
+        Node::Ramp(Box::new(Node::Facing), vec![
+            (0.0, Color::new(0.0, 0.0, 0.0, false)),
+            (1.0, Color::new(1.0, 1.0, 1.0, false))
+        ])
+    );
+    assert_eq!(node, expected);
+
+    assert!(matches!(parse("multiply(checker(4), noise(2, 3))", &mut cache), Ok(Node::Multiply(_, _))));
+    assert_eq!(parse("position(uv)", &mut cache), Ok(Node::Position(Space::Uv)));
+}
+
+#[test]
+fn test_parse_errors() {
+    let mut cache = TextureCache::new();
+
+    assert!(parse("mix(1, 2)", &mut cache).is_err());
+    assert!(parse("rgb(1, 0, 0", &mut cache).is_err());
+    assert!(parse("sparkles", &mut cache).is_err());
+    assert!(parse("image(\"missing.png\")", &mut cache).is_err());
+    assert!(parse("0.5 0.5", &mut cache).is_err());
+    assert!(parse("fresnel(0)", &mut cache).is_err());
+    assert!(parse("fresnel(-1.5)", &mut cache).is_err());
+
+    // deep nesting is rejected instead of overflowing the stack
+    let nested = |depth: usize| format!("{}0.5{}", "invert(".repeat(depth), ")".repeat(depth));
+    assert!(parse(&nested(64), &mut cache).is_ok());
+    assert!(parse(&nested(100000), &mut cache).is_err());
+}
+
+#[test]
+fn test_blend_materials() {
+    let a = Material::new(Color::new(1.0, 0.0, 0.0, false), 0.2, 0.0);
+    let b = Material::new(Color::new(0.0, 0.0, 1.0, false), 0.6, 0.0);
+    let blended = Material::blend(&a, &b, Node::Facing);
+
+    let parameters: Vec<Parameter> = blended.textures.iter().map(|(parameter, _)| *parameter).collect();
+    assert_eq!(parameters, vec![Parameter::Albedo, Parameter::Roughness]);
+    assert!(blended.textures.iter().all(|(_, texture)| matches!(texture, Texture::Graph(_))));
+
+    let record = hit_record(&blended);
+    let evaluated = blended.evaluate(&record, view());
+    assert!((evaluated.albedo.x() - 0.2).abs() < EPSILON);
+    assert!((evaluated.albedo.z() - 0.8).abs() < EPSILON);
+    assert!((evaluated.roughness - 0.52).abs() < EPSILON);
+}
@@ -0,0 +1,80 @@
+use raytracer::sky::*;
+use raytracer::background::Background;
+use raytracer::vec3::Vec3;
+
+#[test]
+fn test_solar_position() {
+    // equinox noon at 45 degrees north, the sun is due south
+    let (elevation, azimuth) = solar_position(80, 12.0, 45.0);
+    assert!((elevation - 45.0).abs() < 1.0);
+    assert!((azimuth - 180.0).abs() < 1e-3);
+
+    // summer mornings have the sun in the east, afternoons in the west
+    let (morning_elevation, morning_azimuth) = solar_position(172, 9.0, 45.0);
+    let (afternoon_elevation, afternoon_azimuth) = solar_position(172, 15.0, 45.0);
+    assert!((morning_elevation - afternoon_elevation).abs() < 1e-9);
+    assert!(morning_azimuth > 90.0 && morning_azimuth < 180.0);
+    assert!((morning_azimuth + afternoon_azimuth - 360.0).abs() < 1e-9);
+
+    let east = sun_direction(0.0, 90.0);
+    assert!((east - Vec3::new(1.0, 0.0, 0.0, false)).length() < 1e-12);
+}
+
+#[test]
+fn test_sky_colors() {
+    let sky = Sky::new(60.0, 180.0, 3.0);
+    let zenith = sky.value(Vec3::new(0.0, 1.0, 0.0, false));
+
+    // a clear sky is blue and brightest around the sun, the ground is black
+    assert!(zenith.z() > zenith.x());
+    assert!(sky.value(Vec3::new(0.0, 0.5, 1.0, false)).y() > sky.value(Vec3::new(0.0, 0.5, -1.0, false)).y());
+    assert_eq!(sky.value(Vec3::new(0.3, -0.2, 1.0, false)).y(), 0.0);
+
+    // the sun disk outshines the sky and reddens near the horizon
+    let noon_sun = sky.value(sky.sun_direction());
+    assert!(noon_sun.y() > 1000.0 * zenith.y());
+
+    let sunset = Sky::new(3.0, 270.0, 3.0);
+    let sunset_sun = sunset.value(sunset.sun_direction());
+    assert!(sunset_sun.x() / sunset_sun.z() > 2.0 * noon_sun.x() / noon_sun.z());
+
+    let disk_edge = (SUN_ANGULAR_RADIUS * 1.1).to_radians();
+    let beside_sun = (sky.sun_direction() + Vec3::new(disk_edge, 0.0, 0.0, false)).normalized();
+    assert!(sky.value(beside_sun).y() < 0.01 * noon_sun.y());
+}
+
+#[test]
+fn test_sky_from_time() {
+    let sky = Sky::from_time(80, 12.0, 45.0, 4.0);
+    let expected = sun_direction(solar_position(80, 12.0, 45.0).0, 180.0);
+    assert!((sky.sun_direction() - expected).length() < 1e-6);
+    assert_eq!(sky.turbidity(), 4.0);
+}
+
+#[test]
+fn test_sky_sampling() {
+    let sky = Sky::new(30.0, 120.0, 2.5);
+    let cos_radius = SUN_ANGULAR_RADIUS.to_radians().cos();
+    let mut in_sun = 0;
+    let count = 400;
+
+    for i in 0..count {
+        let u = ((i as f64 + 0.5) / count as f64, ((i * 7919) % count) as f64 / count as f64);
+        let (direction, pdf) = sky.sample(u);
+
+        assert!(pdf > 0.0);
+        assert!((sky.pdf(direction) - pdf).abs() <= 1e-9 * pdf);
+        assert!(direction.y() > 0.0);
+
+        if direction.dot(sky.sun_direction()) >= cos_radius {
+            in_sun += 1;
+        }
+    }
+
+    // half the samples go to the sun disk
+    assert!((in_sun as f64 / count as f64 - 0.5).abs() < 0.02);
+
+    // once the sun has set there is nothing to sample
+    let night = Background::Sky(Sky::new(-10.0, 0.0, 3.0));
+    assert_eq!(night.value(Vec3::new(0.0, 1.0, 0.0, false)).y(), 0.0);
+}
@@ -80,4 +80,39 @@ fn test_ray_origin_inside_sphere() {
     assert!(hit_record.t_min > 0.0);
     assert_eq!(hit_record.point, Vec3::new(0.0, 0.0, -0.5, true));
     assert_eq!(hit_record.normal, Vec3::new(0.0, 0.0, 1.0, true));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_uv_mapping() {
+    let material = Material::new(Color::new(0.0, 0.0, 0.0, false), 0.0, 0.0);
+    let sphere = Sphere::new(Vec3::new(1.0, 0.0, 0.0, true), 2.0, material, None);
+
+    let ((u, v), _, _) = sphere.uv_at(Vec3::new(3.0, 0.0, 0.0, true));
+    assert!(u.abs() < 1e-12 && (v - 0.5).abs() < 1e-12);
+
+    let ((u, v), _, _) = sphere.uv_at(Vec3::new(1.0, 0.0, 2.0, true));
+    assert!((u - 0.25).abs() < 1e-12 && (v - 0.5).abs() < 1e-12);
+
+    let ((_, v), _, _) = sphere.uv_at(Vec3::new(1.0, 2.0, 0.0, true));
+    assert!((v - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_uv_derivatives() {
+    let material = Material::new(Color::new(0.0, 0.0, 0.0, false), 0.0, 0.0);
+    let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0, true), 1.0, material, None);
+
+    // walk along the surface and compare with the finite differences of uv
+    let point = |u: f64, v: f64| {
+        let (phi, theta) = (2.0 * std::f64::consts::PI * u, std::f64::consts::PI * v);
+        Vec3::new(theta.sin() * phi.cos(), -theta.cos(), theta.sin() * phi.sin(), true)
+    };
+    let h = 1e-6;
+    let ((u, v), dpdu, dpdv) = sphere.uv_at(point(0.3, 0.7));
+    assert!((u - 0.3).abs() < 1e-9 && (v - 0.7).abs() < 1e-9);
+
+    let du = (point(u + h, v) - point(u - h, v)) / (2.0 * h);
+    let dv = (point(u, v + h) - point(u, v - h)) / (2.0 * h);
+    assert!((du - dpdu).length() < 1e-6);
+    assert!((dv - dpdv).length() < 1e-6);
+}
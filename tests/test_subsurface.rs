@@ -0,0 +1,66 @@
+use raytracer::subsurface::*;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-10;
+
+#[test]
+fn test_single_scattering_albedo() {
+    let subsurface = Subsurface::new(Color::new(0.0, 0.5, 0.99, false), Color::new(1.0, 1.0, 1.0, false));
+    let albedo = subsurface.single_scattering_albedo();
+    assert!(albedo.x().abs() < 1e-3);
+    assert!(albedo.y() > 0.5 && albedo.y() < albedo.z());
+    assert!(albedo.z() < 1.0);
+}
+
+#[test]
+fn test_extinction_scales_with_mean_free_path() {
+    let near = Subsurface::new(Color::new(0.8, 0.8, 0.8, false), Color::new(0.5, 0.5, 0.5, false));
+    let far = Subsurface::new(Color::new(0.8, 0.8, 0.8, false), Color::new(1.0, 1.0, 1.0, false));
+    assert!((near.extinction().x() - 2.0 * far.extinction().x()).abs() < EPSILON);
+}
+
+#[test]
+fn test_sample_event_gray() {
+    let subsurface = Subsurface::new(Color::new(0.7, 0.7, 0.7, false), Color::new(0.1, 0.1, 0.1, false));
+    let albedo = subsurface.single_scattering_albedo();
+
+    for _ in 0..100 {
+        match subsurface.sample_event(1.0) {
+            VolumeEvent::Scatter { distance, weight } => {
+                assert!(distance < 1.0);
+                assert!((weight.x() - albedo.x()).abs() < 1e-9);
+            }
+            VolumeEvent::Pass { weight } => {
+                assert!((weight.x() - 1.0).abs() < 1e-9);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sample_event_at_boundary() {
+    let subsurface = Subsurface::new(Color::new(0.9, 0.5, 0.2, false), Color::new(1.0, 0.5, 0.2, false));
+
+    match subsurface.sample_event(0.0) {
+        VolumeEvent::Pass { weight } => assert_eq!(weight, Color::new(1.0, 1.0, 1.0, false)),
+        VolumeEvent::Scatter { .. } => panic!("Scattered beyond the boundary")
+    }
+}
+
+#[test]
+fn test_transmittance() {
+    let sigma_t = Color::new(0.0, 1.0, 2.0, false);
+    let t = transmittance(sigma_t, 0.5);
+    assert!((t.x() - 1.0).abs() < EPSILON);
+    assert!((t.y() - (-0.5_f64).exp()).abs() < EPSILON);
+    assert!((t.z() - (-1.0_f64).exp()).abs() < EPSILON);
+}
+
+#[test]
+fn test_sample_isotropic() {
+    for _ in 0..100 {
+        assert!((sample_isotropic().length() - 1.0).abs() < 1e-9);
+    }
+}
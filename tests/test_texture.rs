@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use raytracer::texture::*;
+use raytracer::image::{Image, ColorSpace};
+use raytracer::material::{Material, Parameter};
+use raytracer::sphere::Sphere;
+use raytracer::ray::Ray;
+use raytracer::hit::Hit;
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-9;
+
+fn gray(value: f64) -> Color {
+    Color::new(value, value, value, false)
+}
+
+fn ramp() -> Arc<Image> {
+    Arc::new(Image::new(2, 1, vec![gray(0.0), gray(1.0)]))
+}
+
+#[test]
+fn test_bilinear_filtering() {
+    let texture = ImageTexture::new(ramp(), WrapMode::Clamp);
+    assert!(texture.sample((0.25, 0.5)).x().abs() < EPSILON);
+    assert!((texture.sample((0.5, 0.5)).x() - 0.5).abs() < EPSILON);
+    assert!((texture.sample((0.625, 0.5)).x() - 0.75).abs() < EPSILON);
+    assert!((texture.sample((2.0, 0.5)).x() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn test_wrap_modes() {
+    let repeat = ImageTexture::new(ramp(), WrapMode::Repeat);
+    let mirror = ImageTexture::new(ramp(), WrapMode::Mirror);
+
+    // halfway between the last texel and the wrapped first one
+    assert!((repeat.sample((1.0, 0.5)).x() - 0.5).abs() < EPSILON);
+    assert!((repeat.sample((1.25, 0.5)).x() - repeat.sample((0.25, 0.5)).x()).abs() < EPSILON);
+    assert!((mirror.sample((1.0, 0.5)).x() - 1.0).abs() < EPSILON);
+    assert!((mirror.sample((1.25, 0.5)).x() - 1.0).abs() < EPSILON);
+    assert!(mirror.sample((1.75, 0.5)).x().abs() < EPSILON);
+}
+
+#[test]
+fn test_texture_cache_shares_images() {
+    let path = std::env::temp_dir().join("raytracer_test_texture_cache.ppm");
+    std::fs::write(&path, "P3 1 1 255 255 255 255").unwrap();
+
+    let mut cache = TextureCache::new();
+    let a = cache.load(&path, ColorSpace::Srgb).unwrap();
+    let b = cache.load(&path, ColorSpace::Srgb).unwrap();
+    let c = cache.load(&path, ColorSpace::Linear).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(!Arc::ptr_eq(&a, &c));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.load("missing.ppm", ColorSpace::Srgb).is_err());
+}
+
+#[test]
+fn test_material_evaluates_textures() {
+    let material = Material {
+        textures: vec![
+            (Parameter::Albedo, Texture::image(ramp(), WrapMode::Clamp)),
+            (Parameter::Roughness, Texture::Constant(gray(0.8)))
+        ],
+        ..Material::new(gray(0.5), 0.1, 0.0)
+    };
+    let sphere = Sphere::new(Vec3::new(0.0, 0.0, 0.0, true), 1.0, material, None);
+
+    // the +x axis lies at u = 0, where the clamped ramp is black
+    let ray = Ray::new(Vec3::new(3.0, 0.0, 0.0, true), Vec3::new(-1.0, 0.0, 0.0, false));
+    let hit_record = sphere.hit(&ray, 0.0, f64::INFINITY).unwrap();
+    let evaluated = hit_record.material.evaluate(&hit_record, -ray.direction());
+
+    assert!(evaluated.albedo.x().abs() < EPSILON);
+    assert!((evaluated.roughness - 0.8).abs() < EPSILON);
+    assert!((sphere.material.roughness - 0.1).abs() < EPSILON);
+}
+
+fn checkerboard(size: usize) -> Arc<Image> {
+    let pixels = (0..size * size).map(|i| gray(((i % size + i / size) % 2) as f64)).collect();
+    Arc::new(Image::new(size, size, pixels))
+}
+
+#[test]
+fn test_mip_levels() {
+    let image = checkerboard(8);
+    assert_eq!(image.levels(), 4);
+    assert_eq!((image.level(1).width, image.level(3).width), (4, 1));
+    assert!((image.level(1).pixel(2, 3).x() - 0.5).abs() < EPSILON);
+
+    let odd = Image::new(3, 1, vec![gray(0.0), gray(0.3), gray(0.9)]);
+    assert_eq!(odd.levels(), 2);
+    assert!((odd.level(1).pixel(0, 0).x() - 0.4).abs() < EPSILON);
+
+    // a single pixel has no coarser level
+    let single = Image::new(1, 1, vec![gray(0.7)]);
+    assert_eq!(single.levels(), 1);
+    assert!(std::ptr::eq(single.level(2), &single));
+}
+
+#[test]
+fn test_footprint_filtering() {
+    for filter in [Filter::Trilinear, Filter::Ewa] {
+        let texture = ImageTexture { filter, ..ImageTexture::new(checkerboard(64), WrapMode::Repeat) };
+
+        // a footprint covering the whole texture averages the checkerboard
+        let wide = texture.sample_footprint((0.3, 0.6), (1.0, 0.0), (0.0, 1.0));
+        assert!((wide.x() - 0.5).abs() < 1e-6);
+
+        // without a footprint the finest level is used
+        let sharp = texture.sample_footprint((0.5 / 64.0, 1.0 - 0.5 / 64.0), (0.0, 0.0), (0.0, 0.0));
+        assert!(sharp.x().abs() < 1e-6);
+    }
+
+    // a long thin footprint blurs along its major axis only
+    let stripes = Arc::new(Image::new(64, 1, (0..64).map(|i| gray((i % 2) as f64)).collect()));
+    let texture = ImageTexture { filter: Filter::Ewa, ..ImageTexture::new(stripes, WrapMode::Repeat) };
+    let blurred = texture.sample_footprint((0.5 / 64.0, 0.5), (0.25, 0.0), (0.0, 0.001));
+    assert!((blurred.x() - 0.5).abs() < 0.05);
+}
@@ -0,0 +1,59 @@
+use raytracer::thinfilm::*;
+use raytracer::brdf::{fresnel_dielectric, fresnel_conductor};
+use raytracer::vec3::Vec3;
+
+use Vec3 as Color;
+
+const EPSILON: f64 = 1e-9;
+
+#[test]
+fn test_zero_thickness_matches_fresnel() {
+    let film = ThinFilm::new(0.0, 1.33);
+
+    for i in 0..10 {
+        let cos_theta = 0.05 + 0.1 * i as f64;
+        let reflectance = film.reflectance_at(cos_theta, 550.0, 1.0, 1.5, 0.0);
+        assert!((reflectance - fresnel_dielectric(cos_theta, 1.0 / 1.5)).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn test_zero_thickness_matches_conductor() {
+    let eta = Color::new(0.143, 0.374, 1.442, false);
+    let k = Color::new(3.983, 2.385, 1.603, false);
+    let film = ThinFilm::new(0.0, 1.4);
+    let reflectance = film.reflectance(0.6, 1.0, eta, k);
+    let expected = fresnel_conductor(0.6, eta, k);
+
+    for c in 0..3 {
+        assert!((reflectance[c] - expected[c]).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_quarter_wave_coating_cancels_reflection() {
+    let ior = 1.5_f64.sqrt();
+    let film = ThinFilm::new(550.0 / (4.0 * ior), ior);
+    assert!(film.reflectance_at(1.0, 550.0, 1.0, 1.5, 0.0) < 1e-12);
+    assert!(film.reflectance_at(1.0, 450.0, 1.0, 1.5, 0.0) > 1e-4);
+}
+
+#[test]
+fn test_half_wave_layer_is_absent() {
+    let film = ThinFilm::new(550.0 / (2.0 * 1.8), 1.8);
+    let reflectance = film.reflectance_at(1.0, 550.0, 1.0, 1.5, 0.0);
+    assert!((reflectance - fresnel_dielectric(1.0, 1.0 / 1.5)).abs() < EPSILON);
+}
+
+#[test]
+fn test_soap_film_normal_incidence() {
+    // closed form reflectance of a free-standing film at normal incidence
+    let film = ThinFilm::new(400.0, 1.33);
+    let r2 = ((1.0_f64 - 1.33) / (1.0 + 1.33)).powf(2.0);
+
+    for wavelength in [420.0, 500.0, 580.0, 660.0] {
+        let delta = 4.0 * std::f64::consts::PI * 1.33 * 400.0 / wavelength;
+        let expected = 2.0 * r2 * (1.0 - delta.cos()) / (1.0 + r2 * r2 - 2.0 * r2 * delta.cos());
+        assert!((film.reflectance_at(1.0, wavelength, 1.0, 1.0, 0.0) - expected).abs() < EPSILON);
+    }
+}
@@ -164,4 +164,32 @@ fn test_vec3_transform_vector() {
     ];
     let transformed = v.transform(&matrix);
     assert_eq!(transformed, Vec3::new(1.0, 2.0, 3.0, false));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_vec3_refract() {
+    let v = Vec3::new(1.0, -1.0, 0.0, false).normalized();
+    let normal = Vec3::new(0.0, 1.0, 0.0, false);
+    let refracted = v.refract(normal, 1.0).unwrap();
+    assert!(approx_eq(refracted.x(), v.x()));
+    assert!(approx_eq(refracted.y(), v.y()));
+    assert!(approx_eq(refracted.z(), v.z()));
+}
+
+#[test]
+fn test_vec3_refract_total_internal_reflection() {
+    let v = Vec3::new(1.0, -0.2, 0.0, false).normalized();
+    let normal = Vec3::new(0.0, 1.0, 0.0, false);
+    assert!(v.refract(normal, 1.5).is_none());
+}
+
+#[test]
+fn test_vec3_orthonormal_basis() {
+    let n = Vec3::new(1.0, 2.0, -3.0, false).normalized();
+    let (t, b) = n.orthonormal_basis();
+    assert!(approx_eq(t.length(), 1.0));
+    assert!(approx_eq(b.length(), 1.0));
+    assert!(approx_eq(t.dot(n), 0.0));
+    assert!(approx_eq(b.dot(n), 0.0));
+    assert!(approx_eq(t.dot(b), 0.0));
+}